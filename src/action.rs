@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{Move, Node, Partition};
+
+/// An action to take to get from one assignment to another.
+///
+/// It is serialized as a JSON object tagged by `kind`, e.g.
+/// `{ "kind": "move_replica", "partition": 1, "from": "node_1", "to": "node_2" }`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Action {
+    /// Add a new replica of an existing partition on `node`.
+    AddReplica { partition: Partition, node: Node },
+
+    /// Drop the replica of a partition on `node`.
+    DropReplica { partition: Partition, node: Node },
+
+    /// Move the replica of a partition from node `from` to node `to`.
+    MoveReplica { partition: Partition, from: Node, to: Node },
+
+    /// Promote the replica on node `to` to the leader (first replica) of a partition, in place of
+    /// node `from`. Both nodes already hold a replica, so there is no data to move.
+    PromoteLeader { partition: Partition, from: Node, to: Node },
+
+    /// Create a new partition with replicas on `nodes`.
+    CreatePartition { partition: Partition, nodes: Vec<Node> },
+
+    /// Delete a partition with replicas on `nodes`.
+    DeletePartition { partition: Partition, nodes: Vec<Node> },
+}
+
+impl Action {
+    /// The kind of the action, the same as the `kind` tag in JSON.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::AddReplica { .. } => "add_replica",
+            Self::DropReplica { .. } => "drop_replica",
+            Self::MoveReplica { .. } => "move_replica",
+            Self::PromoteLeader { .. } => "promote_leader",
+            Self::CreatePartition { .. } => "create_partition",
+            Self::DeletePartition { .. } => "delete_partition",
+        }
+    }
+
+    /// The partition the action applies to.
+    pub fn partition(&self) -> Partition {
+        match self {
+            Self::AddReplica { partition, .. }
+            | Self::DropReplica { partition, .. }
+            | Self::MoveReplica { partition, .. }
+            | Self::PromoteLeader { partition, .. }
+            | Self::CreatePartition { partition, .. }
+            | Self::DeletePartition { partition, .. } => *partition,
+        }
+    }
+
+    /// Counts the actions by kind.
+    pub fn counts(actions: &[Action]) -> BTreeMap<&'static str, usize> {
+        let mut counts: BTreeMap<&'static str, usize> = Default::default();
+        for action in actions {
+            *counts.entry(action.kind()).or_default() += 1;
+        }
+
+        counts
+    }
+}
+
+impl From<Move> for Action {
+    fn from(m: Move) -> Self {
+        Self::MoveReplica {
+            partition: m.partition,
+            from: m.from,
+            to: m.to,
+        }
+    }
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let nodes_str = |ns: &[Node]| ns.iter().map(|n| n.to_string()).collect::<Vec<_>>().join(", ");
+        match self {
+            Self::AddReplica { partition, node } => {
+                write!(f, "Add {partition} replica on {node}")
+            }
+            Self::DropReplica { partition, node } => {
+                write!(f, "Drop {partition} replica on {node}")
+            }
+            Self::MoveReplica { partition, from, to } => {
+                write!(f, "Move {partition} from {from} to {to}")
+            }
+            Self::PromoteLeader { partition, from, to } => {
+                write!(f, "Promote {to} to leader of {partition} in place of {from}")
+            }
+            Self::CreatePartition { partition, nodes } => {
+                write!(f, "Create {partition} on [{}]", nodes_str(nodes))
+            }
+            Self::DeletePartition { partition, nodes } => {
+                write!(f, "Delete {partition} on [{}]", nodes_str(nodes))
+            }
+        }
+    }
+}
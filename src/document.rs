@@ -0,0 +1,276 @@
+use std::collections::{BTreeMap, BTreeSet};
+use serde::{Deserialize, Serialize};
+use log::debug;
+use anyhow::{bail, Context, Error, Result};
+
+use crate::{Assignment, Move, Node, Partition, Target, Topology};
+
+/// A document of many named assignments, e.g. the tables of a Mnesia cluster or the topics of a
+/// Kafka cluster, sharing the same nodes.
+///
+/// It is serialized as a JSON object of the assignments by name, e.g.
+/// `{ "mqtt_session": { "1": ["node_1", "node_2"], ... }, "mqtt_route": { ... } }`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Document(BTreeMap<String, Assignment>);
+
+impl std::str::FromStr for Document {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let document: Document = serde_json::from_str(s)?;
+        Ok(document)
+    }
+}
+
+impl From<BTreeMap<String, Assignment>> for Document {
+    fn from(tables: BTreeMap<String, Assignment>) -> Self {
+        Self(tables)
+    }
+}
+
+impl FromIterator<(String, Assignment)> for Document {
+    fn from_iter<I: IntoIterator<Item = (String, Assignment)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl Document {
+    /// The assignments by name.
+    pub fn tables(&self) -> &BTreeMap<String, Assignment> {
+        &self.0
+    }
+
+    /// The assignment named `table`.
+    pub fn get(&self, table: &str) -> Option<&Assignment> {
+        self.0.get(table)
+    }
+
+    /// All the nodes holding replicas in any of the assignments.
+    pub fn nodes(&self) -> BTreeSet<&Node> {
+        self.0.values().flat_map(|a| a.nodes()).collect()
+    }
+
+    /// The total number of replicas on each node across all the assignments.
+    pub fn totals(&self) -> BTreeMap<Node, usize> {
+        let mut totals: BTreeMap<Node, usize> = Default::default();
+        for assignment in self.0.values() {
+            for ns in assignment.0.values() {
+                for n in ns {
+                    *totals.entry(n.clone()).or_default() += 1;
+                }
+            }
+        }
+
+        totals
+    }
+
+    pub fn into_inner(self) -> BTreeMap<String, Assignment> {
+        self.0
+    }
+
+    /// Initializes an assignment of each of `tables` on the same nodes, as
+    /// [`Assignment::init_with_topology`] does.
+    ///
+    /// The round-robin of each table starts where the previous table ends, so the extra partitions
+    /// are rotated across the nodes, and then the totals are balanced by
+    /// [`Document::balance_totals`].
+    pub fn init_with_topology(
+        tables: &[String],
+        nodes: &[Node],
+        partitions: usize,
+        replication_factor: usize,
+        starts_with_zero: bool,
+        topology: &Topology,
+    ) -> Result<Self> {
+        let mut document = BTreeMap::new();
+        let mut offset = 0;
+        for table in tables {
+            let assignment = Assignment::init_rotated(
+                nodes,
+                partitions,
+                replication_factor,
+                starts_with_zero,
+                topology,
+                offset,
+            )?;
+            if document.insert(table.clone(), assignment).is_some() {
+                bail!("Provided duplicated table `{table}`");
+            }
+            offset += partitions;
+        }
+
+        Ok(Self(document).balance_totals(Default::default(), topology).0)
+    }
+
+    /// Rebalances every assignment as is, see [`Assignment::rebalance`].
+    ///
+    /// Returns the new document and the moves of each assignment to get there.
+    pub fn rebalance(self, topology: &Topology) -> (Self, BTreeMap<String, Vec<Move>>) {
+        let mut tables = BTreeMap::new();
+        let mut moves = BTreeMap::new();
+        for (name, assignment) in self.0 {
+            let (assignment, ms) = assignment.rebalance(topology);
+            tables.insert(name.clone(), assignment);
+            moves.insert(name, ms);
+        }
+
+        (Self(tables), moves)
+    }
+
+    /// The balance targets of the total number of replicas on each node across all the
+    /// assignments, which are the sums of the targets of each assignment.
+    pub fn total_targets(&self, topology: &Topology) -> BTreeMap<Node, Target> {
+        let mut ideals: BTreeMap<Node, f64> = Default::default();
+        for assignment in self.0.values() {
+            for (n, t) in assignment.targets(topology) {
+                *ideals.entry(n).or_default() += t.ideal;
+            }
+        }
+
+        ideals
+            .into_iter()
+            .map(|(n, ideal)| {
+                // Away from the floating point errors of the sums
+                let rounded = ideal.round();
+                let ideal = if (ideal - rounded).abs() < 1e-9 { rounded } else { ideal };
+                (n, Target { ideal, lower: ideal.floor() as usize, upper: ideal.ceil() as usize })
+            })
+            .collect()
+    }
+
+    /// Adds nodes to every assignment.
+    ///
+    /// Returns the new document and the moves of each assignment to get there.
+    pub fn add_nodes_with_topology(self, adds: &[Node], topology: &Topology)
+    -> Result<(Self, BTreeMap<String, Vec<Move>>)>
+    {
+        let mut tables = BTreeMap::new();
+        let mut moves = BTreeMap::new();
+        for (name, assignment) in self.0 {
+            let (assignment, ms) = assignment
+                .add_nodes_with_topology(adds.to_vec(), topology)
+                .with_context(|| format!("Failed to add nodes to `{name}`"))?;
+            tables.insert(name.clone(), assignment);
+            moves.insert(name, ms);
+        }
+
+        Ok((Self(tables), moves))
+    }
+
+    /// Removes nodes from every assignment holding any of them, keeping `replication_factor` if
+    /// given, otherwise the replication factor of each assignment.
+    ///
+    /// Returns the new document and the moves of each assignment to get there.
+    pub fn remove_nodes_with_topology(
+        &self,
+        removes: &[Node],
+        replication_factor: Option<usize>,
+        topology: &Topology,
+    ) -> Result<(Self, BTreeMap<String, Vec<Move>>)>
+    {
+        let mut tables = BTreeMap::new();
+        let mut moves = BTreeMap::new();
+        for (name, assignment) in &self.0 {
+            let removes = removes
+                .iter()
+                .filter(|n| assignment.contains_node(n))
+                .cloned()
+                .collect::<Vec<_>>();
+            let (assignment, ms) = if removes.is_empty() {
+                (assignment.clone(), vec![])
+            } else {
+                let factor = replication_factor.unwrap_or(assignment.replication_factor());
+                assignment
+                    .remove_nodes_with_topology(&removes, factor, topology)
+                    .with_context(|| format!("Failed to remove nodes from `{name}`"))?
+            };
+            tables.insert(name.clone(), assignment);
+            moves.insert(name.clone(), ms);
+        }
+
+        Ok((Self(tables), moves))
+    }
+
+    /// Moves replicas across the assignments, from the nodes with the most replicas in total to
+    /// the nodes with the least, proportional to their weights, until the totals of any two nodes
+    /// differ by at most one as far as possible.
+    ///
+    /// Every move keeps the number of partitions on both nodes within their targets of that
+    /// assignment, and keeps the zones spread of the partition, so each assignment stays balanced
+    /// on its own.
+    pub fn balance_totals(
+        mut self,
+        mut moves: BTreeMap<String, Vec<Move>>,
+        topology: &Topology,
+    ) -> (Self, BTreeMap<String, Vec<Move>>) {
+        let targets = self.0
+            .iter()
+            .map(|(name, a)| (name.clone(), a.targets(topology)))
+            .collect::<BTreeMap<_, _>>();
+        let ideals = self.total_targets(topology)
+            .into_iter()
+            .map(|(n, t)| (n, t.ideal))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut maps = self.0
+            .iter()
+            .map(|(name, a)| (name.clone(), a.nodes_map()))
+            .collect::<BTreeMap<_, _>>();
+        let mut totals = self.totals();
+        let deviation = |n: &Node, totals: &BTreeMap<Node, usize>| {
+            totals.get(n).copied().unwrap_or(0) as f64 - ideals[n]
+        };
+
+        loop {
+            let mut nodes = ideals.keys().collect::<Vec<_>>();
+            nodes.sort_by(|n1, n2| deviation(n1, &totals).total_cmp(&deviation(n2, &totals)));
+
+            // Find a partition of any assignment on the node of the most replicas in total, which
+            // can go to the node of the least without breaking the assignment balance
+            let found = nodes.iter().rev().find_map(|&upper| {
+                nodes
+                    .iter()
+                    .take_while(|&&lower| deviation(upper, &totals) - deviation(lower, &totals) > 1.0)
+                    .find_map(|&lower| {
+                        self.0.iter().find_map(|(name, a)| {
+                            let (ts, map) = (&targets[name], &maps[name]);
+                            let (Some(ups), Some(lps)) = (map.get(upper), map.get(lower)) else {
+                                return None;
+                            };
+                            if ups.len() <= ts[upper].lower || lps.len() >= ts[lower].upper {
+                                return None;
+                            }
+                            let zones_count = topology.zones(map.keys()).len();
+                            ups.difference(lps)
+                                .find(|p| a.keeps_spread(p, upper, lower, topology, zones_count))
+                                .map(|p| (name.clone(), *p, upper.clone(), lower.clone()))
+                        })
+                    })
+            });
+
+            let Some((name, p, upper, lower)) = found else {
+                break;
+            };
+
+            debug!("Move {p} of `{name}` from {upper} to {lower} for the totals");
+            move_replica(self.0.get_mut(&name).unwrap(), p, &upper, &lower);
+            let map = maps.get_mut(&name).unwrap();
+            map.get_mut(&upper).unwrap().remove(&p);
+            map.get_mut(&lower).unwrap().insert(p);
+            *totals.entry(upper.clone()).or_default() -= 1;
+            *totals.entry(lower.clone()).or_default() += 1;
+            moves.entry(name).or_default().push(Move { partition: p, from: upper, to: lower });
+        }
+
+        (self, moves)
+    }
+}
+
+fn move_replica(assignment: &mut Assignment, p: Partition, from: &Node, to: &Node) {
+    if let Some(ns) = assignment.0.get_mut(&p) {
+        for n in ns.iter_mut().filter(|n| *n == from) {
+            *n = to.clone();
+        }
+    }
+}
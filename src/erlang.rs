@@ -0,0 +1,119 @@
+use std::collections::BTreeSet;
+
+use crate::{Action, Assignment, Partition, Plan, Step};
+
+/// Quotes `s` as an Erlang atom, e.g. `'emqx@10.0.0.1'`.
+pub fn atom(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// The Mnesia fragment index of `partition`, which always starts with 1, while the partition ids
+/// start with `first_id`, e.g. partition 0 is fragment 1 for the assignments starting with 0.
+pub fn frag_index(partition: Partition, first_id: u32) -> u32 {
+    partition.id().saturating_sub(first_id) + 1
+}
+
+/// The name of the table holding the fragment of `partition` of `table`, which is `table` itself
+/// for the first fragment, and `<table>_frag<N>` for the others, as `mnesia_frag` names them.
+///
+/// The partition ids start with `first_id`, see [`frag_index`].
+pub fn frag_table(table: &str, partition: Partition, first_id: u32) -> String {
+    match frag_index(partition, first_id) {
+        1 => atom(table),
+        n => atom(&format!("{table}_frag{n}")),
+    }
+}
+
+impl Assignment {
+    /// The assignment as an Erlang term list in the same shape as `frag_dist` of the Mnesia JSON
+    /// dump, e.g. `[#{frag_index => 1, instances => ['n1', 'n2']}, ...].`, where the fragment
+    /// indexes start with 1 even if the partition ids start with 0.
+    pub fn to_erlang(&self) -> String {
+        let frags = self.0
+            .iter()
+            .map(|(p, ns)| {
+                let instances = ns.iter().map(|n| atom(n.as_str())).collect::<Vec<_>>().join(", ");
+                let index = frag_index(*p, self.first_id());
+                format!("  #{{frag_index => {index}, instances => [{instances}]}}")
+            })
+            .collect::<Vec<_>>()
+            .join(",\n");
+
+        format!("[\n{frags}\n].")
+    }
+}
+
+/// The Erlang script to apply the `plan` of the `actions` on the fragmented Mnesia `table`, ready
+/// to be pasted into a remote shell.
+///
+/// The new fragments are added first with `mnesia:change_table_frag/2`, then the phases of the
+/// plan run in order, copying the replicas with `mnesia:add_table_copy/3` of `storage_type` before
+/// dropping any with `mnesia:del_table_copy/2`, and the deleted fragments are removed last. Every
+/// call is matched against `{atomic, ok}`, so the script stops at the first failure.
+///
+/// The partition ids start with `first_id`, see [`frag_index`].
+pub fn mnesia_script(
+    table: &str,
+    storage_type: &str,
+    first_id: u32,
+    actions: &[Action],
+    plan: &Plan,
+) -> String {
+    let mut lines = vec![];
+    let mut created = BTreeSet::new();
+    let mut deleted = vec![];
+
+    for action in actions {
+        match action {
+            Action::CreatePartition { partition, nodes } => {
+                created.insert(*partition);
+                let nodes = nodes.iter().map(|n| atom(n.as_str())).collect::<Vec<_>>().join(", ");
+                lines.push(format!("%% Create {partition}"));
+                lines.push(format!(
+                    "{{atomic, ok}} = mnesia:change_table_frag({}, {{add_frag, [{nodes}]}}).",
+                    atom(table),
+                ));
+            }
+            Action::DeletePartition { partition, .. } => deleted.push(*partition),
+            _ => {}
+        }
+    }
+
+    for (i, phase) in plan.phases().iter().enumerate() {
+        let mut calls = vec![];
+        for step in &phase.steps {
+            match step {
+                Step::Copy { partition, node, .. } if !created.contains(partition) => {
+                    calls.push(format!(
+                        "{{atomic, ok}} = mnesia:add_table_copy({}, {}, {storage_type}).",
+                        frag_table(table, *partition, first_id),
+                        atom(node.as_str()),
+                    ));
+                }
+                Step::Drop { partition, node } if !deleted.contains(partition) => {
+                    calls.push(format!(
+                        "{{atomic, ok}} = mnesia:del_table_copy({}, {}).",
+                        frag_table(table, *partition, first_id),
+                        atom(node.as_str()),
+                    ));
+                }
+                Step::Switch { .. } => calls.push(format!("%% {step}")),
+                _ => {}
+            }
+        }
+        // The phases of the created or deleted fragments only are done by `change_table_frag`
+        if !calls.is_empty() {
+            lines.push(format!("%% Phase {}, {}", i + 1, phase.stage));
+            lines.extend(calls);
+        }
+    }
+
+    // `del_frag` always deletes the last fragment
+    deleted.sort();
+    for partition in deleted.into_iter().rev() {
+        lines.push(format!("%% Delete {partition}"));
+        lines.push(format!("{{atomic, ok}} = mnesia:change_table_frag({}, del_frag).", atom(table)));
+    }
+
+    lines.join("\n")
+}
@@ -0,0 +1,108 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use anyhow::{anyhow, bail, Error, Result};
+
+use crate::{Assignment, Document, Node, Partition};
+
+/// The partition reassignment JSON of `kafka-reassign-partitions`, e.g.
+/// `{ "version": 1, "partitions": [ { "topic": "foo", "partition": 0, "replicas": [1, 2] }, ... ] }`.
+///
+/// The broker ids are read as the node names, and written back as numbers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KafkaReassignment {
+    pub version: u32,
+    pub partitions: Vec<KafkaPartition>,
+}
+
+/// The replicas of a partition of a topic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KafkaPartition {
+    pub topic: String,
+    pub partition: u32,
+    pub replicas: Vec<Node>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_dirs: Option<Vec<String>>,
+}
+
+/// A [`KafkaReassignment`] to write, with the numeric broker ids.
+#[derive(Serialize)]
+struct KafkaReassignmentOut<'a> {
+    version: u32,
+    partitions: Vec<KafkaPartitionOut<'a>>,
+}
+
+#[derive(Serialize)]
+struct KafkaPartitionOut<'a> {
+    topic: &'a str,
+    partition: u32,
+    replicas: Vec<u64>,
+}
+
+impl std::str::FromStr for KafkaReassignment {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let reassignment: KafkaReassignment = serde_json::from_str(s)?;
+        Ok(reassignment)
+    }
+}
+
+impl KafkaReassignment {
+    /// The distinct topics, in the order they first appear.
+    pub fn topics(&self) -> Vec<&str> {
+        let mut topics: Vec<&str> = vec![];
+        for p in &self.partitions {
+            if !topics.contains(&p.topic.as_str()) {
+                topics.push(&p.topic);
+            }
+        }
+        topics
+    }
+
+    /// The assignments of all the topics.
+    pub fn document(&self) -> Result<Document> {
+        self.topics()
+            .into_iter()
+            .map(|t| Ok((t.to_string(), self.assignment(t)?)))
+            .collect()
+    }
+
+    /// The assignment of `topic`.
+    pub fn assignment(&self, topic: &str) -> Result<Assignment> {
+        let mut partitions: BTreeMap<Partition, Vec<Node>> = Default::default();
+        for p in self.partitions.iter().filter(|p| p.topic == topic) {
+            if partitions.insert(Partition::new(p.partition), p.replicas.clone()).is_some() {
+                bail!("Duplicated partition {} of topic `{topic}`", p.partition);
+            }
+        }
+        if partitions.is_empty() {
+            bail!("Topic `{topic}` not found in the reassignment");
+        }
+
+        Ok(partitions.into())
+    }
+
+    /// The reassignment JSON of `assignments` by topic, where all the nodes must be numeric
+    /// broker ids.
+    pub fn to_json<'a, I>(assignments: I) -> Result<String>
+    where
+        I: IntoIterator<Item = (&'a str, &'a Assignment)>,
+    {
+        let mut partitions = vec![];
+        for (topic, assignment) in assignments {
+            for (p, ns) in assignment.partitions() {
+                let replicas = ns
+                    .iter()
+                    .map(|n| {
+                        n.as_str()
+                            .parse::<u64>()
+                            .map_err(|_| anyhow!("{n} is not a numeric Kafka broker id"))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                partitions.push(KafkaPartitionOut { topic, partition: p.id(), replicas });
+            }
+        }
+
+        Ok(serde_json::to_string_pretty(&KafkaReassignmentOut { version: 1, partitions })?)
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use log::debug;
+
+use crate::{Action, Assignment, Node, Partition, Target, Topology};
+
+impl Assignment {
+    /// The partitions led by each node, i.e. where the node is the first replica. The nodes
+    /// leading no partition are included as well.
+    pub fn leaders(&self) -> BTreeMap<Node, BTreeSet<Partition>> {
+        let mut leaders: BTreeMap<Node, BTreeSet<Partition>> = self
+            .nodes()
+            .into_iter()
+            .map(|n| (n.clone(), Default::default()))
+            .collect();
+        for (p, ns) in &self.0 {
+            if let Some(n) = ns.first() {
+                leaders.entry(n.clone()).or_default().insert(*p);
+            }
+        }
+
+        leaders
+    }
+
+    /// The balance targets of the number of partitions led by each node, proportional to their
+    /// weights in `topology`.
+    pub fn leader_targets(&self, topology: &Topology) -> BTreeMap<Node, Target> {
+        topology.targets(self.nodes(), self.0.len(), self.0.len())
+    }
+
+    /// Reorders the replicas of the partitions, until the number of partitions led by each node
+    /// is within its leader target. No replica is moved to another node.
+    ///
+    /// Returns the new assignment and the [`Action::PromoteLeader`] actions to get there.
+    pub fn balance_leaders(mut self, topology: &Topology) -> (Self, Vec<Action>) {
+        let mut swaps = vec![];
+        let targets = self.leader_targets(topology);
+        let mut leaders = self.leaders();
+
+        while let Some(path) = self.leader_path(&leaders, &targets) {
+            // Shift the leadership along the path, the first node leads one partition less, and
+            // the last node one more, the others stay the same.
+            for (p, from, to) in path {
+                debug!("Swap leader of {p} from {from} to {to}");
+                let ns = self.0.get_mut(&p).unwrap();
+                let i = ns.iter().position(|n| *n == to).unwrap();
+                ns.swap(0, i);
+                leaders.get_mut(&from).unwrap().remove(&p);
+                leaders.get_mut(&to).unwrap().insert(p);
+                swaps.push(Action::PromoteLeader { partition: p, from, to });
+            }
+        }
+
+        (self, swaps)
+    }
+
+    /// Finds a path of leader swaps from an upper bound node to a lower bound node, where each
+    /// step goes from the leader of a partition to another replica of it.
+    fn leader_path(
+        &self,
+        leaders: &BTreeMap<Node, BTreeSet<Partition>>,
+        targets: &BTreeMap<Node, Target>,
+    ) -> Option<Vec<(Partition, Node, Node)>> {
+        let deviation = |n: &Node| leaders[n].len() as f64 - targets[n].ideal;
+
+        let mut nodes = leaders.keys().collect::<Vec<_>>();
+        nodes.sort_by(|n1, n2| deviation(n2).total_cmp(&deviation(n1)));
+
+        for upper in nodes {
+            // BFS over the nodes reachable by swapping leaders
+            let mut prev: BTreeMap<&Node, (Partition, &Node)> = Default::default();
+            let mut queue = VecDeque::from([upper]);
+            let mut reached = vec![];
+            while let Some(n) = queue.pop_front() {
+                reached.push(n);
+                for p in &leaders[n] {
+                    for m in &self.0[p] {
+                        if m != upper && !prev.contains_key(m) {
+                            prev.insert(m, (*p, n));
+                            queue.push_back(m);
+                        }
+                    }
+                }
+            }
+
+            let lower = reached
+                .into_iter()
+                .filter(|&n| deviation(upper) - deviation(n) > 1.0)
+                .filter(|&n| {
+                    leaders[upper].len() > targets[upper].upper
+                        || leaders[n].len() < targets[n].lower
+                })
+                .min_by(|n1, n2| deviation(n1).total_cmp(&deviation(n2)));
+
+            if let Some(mut n) = lower {
+                let mut path = vec![];
+                while let Some((p, from)) = prev.get(n) {
+                    path.push((*p, (*from).clone(), n.clone()));
+                    n = from;
+                }
+                path.reverse();
+                return Some(path);
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,851 @@
+//! An assignment library for partitioning and replicating data across nodes.
+//!
+//! The [`Assignment`] maps every [`Partition`] to the list of [`Node`]s holding its replicas.
+//! It can be initialized with a round-robin strategy, and then be reassigned by adding or removing
+//! nodes, with the balance guarantee that the number of partitions on any two nodes differs by at
+//! most one (`upper bound - lower bound <= 1`).
+//!
+//! ```
+//! use assignment::{Assignment, Node};
+//!
+//! let nodes: Vec<Node> = ["node_1", "node_2", "node_3"].into_iter().map(Node::from).collect();
+//! let assignment = Assignment::init(&nodes, 12, 2, false).unwrap();
+//! let (assignment, moves) = assignment.add_nodes(vec![Node::from("node_4")]).unwrap();
+//! assert_eq!(assignment.nodes().len(), 4);
+//! assert!(!moves.is_empty());
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use serde::{Deserialize, Serialize};
+use log::debug;
+use anyhow::{bail, Error, Result};
+
+mod action;
+mod document;
+mod erlang;
+mod kafka;
+mod leader;
+mod optimal;
+mod plan;
+mod policy;
+mod rendezvous;
+mod replication;
+mod report;
+mod sizes;
+mod snapshot;
+mod strategy;
+mod topology;
+
+pub use action::Action;
+pub use document::Document;
+pub use erlang::{atom, frag_index, frag_table, mnesia_script};
+pub use kafka::{KafkaPartition, KafkaReassignment};
+pub use plan::{batches, Phase, Plan, Stage, Step};
+pub use policy::NodePolicy;
+pub use report::{Report, Severity, Violation};
+pub use sizes::{Cost, MoveCost, Sizes};
+pub use snapshot::{CurrentSnapshot, FragDist, MnesiaSnapshot, TableDistribution};
+pub use strategy::{GreedyStrategy, OptimalStrategy, RendezvousStrategy, Strategy};
+pub use topology::{NodeInfo, Target, Topology};
+
+/// A node which holds replicas of partitions.
+///
+/// It is serialized as a JSON string, and can be deserialized from a number as well, e.g. the
+/// numeric broker ids of Kafka.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Node(String);
+
+/// A partition, identified by its id.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Partition(u32);
+
+/// The assignment of partitions to nodes, the first node of each list is the first replica.
+///
+/// It is serialized as a JSON object, e.g. `{ "1": ["node_1", "node_2"], "2": [...] }`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Assignment(BTreeMap<Partition, Vec<Node>>);
+
+/// A replica of `partition` moved from node `from` to node `to`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Move {
+    pub partition: Partition,
+    pub from: Node,
+    pub to: Node,
+}
+
+type GroupKey = Vec<(Node, usize)>;
+type GroupValue = Vec<(Partition, Vec<Node>)>;
+type Groups = Vec<(GroupKey, GroupValue)>;
+
+fn cal_groups(assignment: &Assignment, groups: &mut Groups) {
+    let mut remains_nodes: BTreeMap<Node, Vec<_>> = Default::default();
+    for (p, ns) in &assignment.0 {
+        for n in ns {
+            let v = remains_nodes.entry(n.clone()).or_default();
+            v.push(*p);
+        }
+    }
+
+    for (group, _pps) in groups.iter_mut() {
+        for (node, len) in group.iter_mut() {
+            *len = remains_nodes.get(node).map(|ps| ps.len()).unwrap();
+        }
+    }
+}
+
+/// The nodes appearing more than once in `nodes`, wherever they are, each reported once in the
+/// order they are first repeated.
+pub fn duplicates<'a, I>(nodes: I) -> Vec<&'a Node>
+where
+    I: IntoIterator<Item = &'a Node>,
+{
+    let mut seen = BTreeSet::new();
+    let mut dups = vec![];
+    for n in nodes {
+        if !seen.insert(n) && !dups.contains(&n) {
+            dups.push(n);
+        }
+    }
+
+    dups
+}
+
+/// Fails with the duplicated names if any node appears more than once in `nodes`.
+pub(crate) fn ensure_unique(nodes: &[Node]) -> Result<()> {
+    let dups = duplicates(nodes);
+    if !dups.is_empty() {
+        let dups = dups.iter().map(|n| n.as_str()).collect::<Vec<_>>().join(", ");
+        bail!("Provided duplicated nodes: {dups}");
+    }
+
+    Ok(())
+}
+
+impl Node {
+    /// Creates a node with the given name.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self(name.into())
+    }
+
+    /// The name of the node.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl<S: AsRef<str>> From<S> for Node {
+    fn from(s: S) -> Self {
+        Self(s.as_ref().to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Name {
+            Str(String),
+            Id(u64),
+        }
+
+        Ok(match Name::deserialize(deserializer)? {
+            Name::Str(s) => Self(s),
+            Name::Id(id) => Self(id.to_string()),
+        })
+    }
+}
+
+impl std::fmt::Display for Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Node({})", self.0)
+    }
+}
+
+impl Partition {
+    /// Creates a partition with the given id.
+    pub fn new(id: u32) -> Self {
+        Self(id)
+    }
+
+    /// The id of the partition.
+    pub fn id(&self) -> u32 {
+        self.0
+    }
+}
+
+impl std::fmt::Display for Partition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Partition({})", self.0)
+    }
+}
+
+impl<N: Into<u32>> From<N> for Partition {
+    fn from(n: N) -> Self {
+        Self(n.into())
+    }
+}
+
+impl FromIterator<(Partition, Vec<Node>)> for Assignment {
+    fn from_iter<T: IntoIterator<Item = (Partition, Vec<Node>)>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl From<BTreeMap<Partition, Vec<Node>>> for Assignment {
+    fn from(partitions: BTreeMap<Partition, Vec<Node>>) -> Self {
+        Self(partitions)
+    }
+}
+
+impl std::str::FromStr for Assignment {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let assignment: Assignment = serde_json::from_str(s)?;
+        Ok(assignment)
+    }
+}
+
+impl Assignment {
+
+    /// Initializes the assignment of `partitions` partitions with `replication_factor` replicas
+    /// each on `nodes` in a round-robin way.
+    ///
+    /// Partition ids start with 0 if `starts_with_zero` is set, otherwise with 1.
+    pub fn init(
+        nodes: &[Node],
+        partitions: usize,
+        replication_factor: usize,
+        starts_with_zero: bool,
+    ) -> Result<Self> {
+        Self::init_with_topology(
+            nodes,
+            partitions,
+            replication_factor,
+            starts_with_zero,
+            &Topology::default(),
+        )
+    }
+
+    /// Same as [`Assignment::init`], but spreads the replicas of each partition across as many
+    /// distinct zones of `topology` as possible.
+    pub fn init_with_topology(
+        nodes: &[Node],
+        partitions: usize,
+        replication_factor: usize,
+        starts_with_zero: bool,
+        topology: &Topology,
+    ) -> Result<Self> {
+        Self::init_rotated(nodes, partitions, replication_factor, starts_with_zero, topology, 0)
+    }
+
+    /// Same as [`Assignment::init_with_topology`], but the round-robin starts at the node
+    /// `offset`, so the extra partitions of the nodes are rotated, e.g. across the tables.
+    pub(crate) fn init_rotated(
+        nodes: &[Node],
+        partitions: usize,
+        replication_factor: usize,
+        starts_with_zero: bool,
+        topology: &Topology,
+        offset: usize,
+    ) -> Result<Self> {
+        if partitions == 0 {
+            bail!("Partitions must not be zero");
+        }
+
+        if replication_factor == 0 {
+            bail!("Replication factor must not be zero");
+        }
+
+        if nodes.is_empty() {
+            bail!("Nodes must not be empty");
+        }
+
+        ensure_unique(nodes)?;
+
+        if nodes.len() < replication_factor {
+            bail!("Nodes must be greater than or equal to replication factor");
+        }
+
+        let n = topology.interleave(nodes);
+        let n = n.iter().cycle()
+            .skip(offset % n.len())
+            .take(partitions * replication_factor)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let assignment = n.windows(replication_factor)
+            .take(partitions)
+            .enumerate()
+            .map(|(i, nodes)| {
+                let partition_id = if starts_with_zero {
+                    i as u32
+                } else {
+                    i as u32 + 1
+                };
+                (Partition(partition_id), Vec::from(nodes))
+            })
+            .collect::<Assignment>();
+
+        let (assignment, moves) = assignment.spread_zones(vec![], nodes, topology);
+        Ok(assignment.balance_boundary(moves, None, topology).0)
+    }
+
+    /// Adds `adds` nodes to the assignment and moves partitions onto them until balanced.
+    ///
+    /// Returns the new assignment and the moves to get there.
+    pub fn add_nodes<I>(self, adds: I) -> Result<(Self, Vec<Move>)>
+    where
+        I: IntoIterator<Item = Node> + Clone,
+        <I as IntoIterator>::IntoIter: Clone,
+    {
+        self.add_nodes_with_topology(adds, &Topology::default())
+    }
+
+    /// Same as [`Assignment::add_nodes`], but keeps the replicas of each partition spread across
+    /// as many distinct zones of `topology` as possible.
+    pub fn add_nodes_with_topology<I>(self, adds: I, topology: &Topology)
+    -> Result<(Self, Vec<Move>)>
+    where
+        I: IntoIterator<Item = Node> + Clone,
+        <I as IntoIterator>::IntoIter: Clone,
+    {
+        self.check_adds(&adds.clone().into_iter().collect::<Vec<_>>())?;
+
+        let nodes = self.nodes().into_iter().cloned().chain(adds.clone()).collect::<Vec<_>>();
+        let (assignment, moves) = self.spread_zones(vec![], &nodes, topology);
+        Ok(assignment.balance_boundary(moves, adds, topology))
+    }
+
+    /// Removes the `remove` node from the assignment and moves its partitions to the remaining
+    /// nodes until balanced.
+    ///
+    /// Returns the new assignment and the moves to get there.
+    pub fn remove_node(&self, remove: &Node, replication_factor: usize)
+    -> Result<(Assignment, Vec<Move>)>
+    {
+        self.remove_node_with_topology(remove, replication_factor, &Topology::default())
+    }
+
+    /// Same as [`Assignment::remove_node`], but keeps the replicas of each partition spread across
+    /// as many distinct zones of `topology` as possible.
+    pub fn remove_node_with_topology(
+        &self,
+        remove: &Node,
+        replication_factor: usize,
+        topology: &Topology,
+    ) -> Result<(Assignment, Vec<Move>)>
+    {
+        self.remove_nodes_with_topology(std::slice::from_ref(remove), replication_factor, topology)
+    }
+
+    /// Removes the `removes` nodes from the assignment at once, and moves their partitions to the
+    /// remaining nodes until balanced.
+    ///
+    /// Each replica on the removed nodes moves exactly once, which is never more than removing
+    /// the nodes one by one, where a replica may move to a node removed later.
+    ///
+    /// Returns the new assignment and the moves to get there.
+    pub fn remove_nodes(&self, removes: &[Node], replication_factor: usize)
+    -> Result<(Assignment, Vec<Move>)>
+    {
+        self.remove_nodes_with_topology(removes, replication_factor, &Topology::default())
+    }
+
+    /// Same as [`Assignment::remove_nodes`], but keeps the replicas of each partition spread
+    /// across as many distinct zones of `topology` as possible.
+    pub fn remove_nodes_with_topology(
+        &self,
+        removes: &[Node],
+        replication_factor: usize,
+        topology: &Topology,
+    ) -> Result<(Assignment, Vec<Move>)>
+    {
+        let mut moves = vec![];
+        let nodes = self.remaining_nodes(removes, replication_factor)?;
+        let zones_count = topology.zones(&nodes).len();
+        let targets = topology.targets(&nodes, self.replicas(), self.0.len());
+
+        // pending: Partition => [Node] removed from it, each of which needs a new replica
+        let mut pending = self.0
+            .iter()
+            .map(|(p, ns)| {
+                let ns = ns.iter().filter(|&n| removes.contains(n)).cloned().collect::<Vec<_>>();
+                (*p, ns)
+            })
+            .filter(|(_p, ns)| !ns.is_empty())
+            .collect::<BTreeMap<_, _>>();
+
+        let mut remains: Assignment = self.0
+            .iter()
+            .map(|(p, ns)| {
+                let ns = ns.iter().filter(|&n| !removes.contains(n)).cloned().collect::<Vec<_>>();
+                (*p, ns)
+            })
+            .collect();
+
+        // Each round gives one new replica to every partition still missing some
+        while !pending.is_empty() {
+            remains.refill(&mut pending, &mut moves, &targets, zones_count, topology)?;
+            pending.retain(|_p, ns| !ns.is_empty());
+        }
+
+        let (remains, moves) = remains.spread_zones(moves, &nodes, topology);
+
+        // If upper bound - lower bound > 1, then need to reassign, just move a partition from
+        // the node with the most partitions to the node with the least partitions.
+        Ok(remains.balance_boundary(moves, None, topology))
+    }
+
+    /// Gives one new replica to each partition in `pending`, taking the place of one of the
+    /// removed nodes of the partition, or fails if no remaining node can take it.
+    fn refill(
+        &mut self,
+        pending: &mut BTreeMap<Partition, Vec<Node>>,
+        moves: &mut Vec<Move>,
+        targets: &BTreeMap<Node, Target>,
+        zones_count: usize,
+        topology: &Topology,
+    ) -> Result<()> {
+        let deviation = |n: &Node, len: usize| len as f64 - targets[n].ideal;
+
+        // partitions_on_remove: Partition => [Node]
+        let partitions_on_remove = pending
+            .keys()
+            .map(|p| (*p, self.0[p].clone()))
+            .collect::<BTreeMap<_, _>>();
+
+        // transform remains to Node => Set(partitions)
+        let remains_nodes = self.nodes_map();
+
+        // Group by alternatives for these partitions
+        let mut groups: HashMap<_, Vec<(_, _)>> = Default::default();
+
+        // px: node => partitions(num) on remove
+        let mut px: HashMap<_, Vec<_>> = Default::default();
+
+        for (p, ns) in &partitions_on_remove {
+            for n in ns {
+                let v = px.entry(n).or_default();
+                v.push(*p);
+            }
+        }
+
+        let px = px.iter().map(|(n, ps)| (n, ps.len())).collect::<HashMap<_, _>>();
+
+        for (p, ns) in &partitions_on_remove {
+            let alters = remains_nodes
+                .iter()
+                .filter(|(_n, ps)| !ps.contains(p))
+                .map(|(n, ps)| (n.clone(), ps.len()))
+                .collect::<Vec<_>>();
+
+            // Only the nodes in the zones not covered yet are alternatives, if there are any
+            let zones = topology.zones(ns);
+            let required = zones_count.min(ns.len() + 1);
+            let spread = alters
+                .iter()
+                .filter(|(n, _len)| !zones.contains(topology.zone(n)))
+                .cloned()
+                .collect::<Vec<_>>();
+            let alters = if zones.len() < required && !spread.is_empty() {
+                spread
+            } else {
+                alters
+            };
+            let v = groups.entry(alters).or_default();
+            v.push((*p, ns.clone()));
+        }
+
+        let mut groups = groups.into_iter().collect::<Vec<_>>();
+        groups.sort_by(|(ns1, _), (ns2, _)| {
+            let sum_ns1 = ns1.iter().map(|(n, _)| px.get(&n).unwrap_or(&0)).sum::<usize>();
+            let sum_ns2 = ns2.iter().map(|(n, _)| px.get(&n).unwrap_or(&0)).sum::<usize>();
+            sum_ns2.cmp(&sum_ns1)
+        });
+
+        let mut refill = |remains: &mut Assignment, p: Partition, mut ns: Vec<Node>, to: Node| {
+            let from = pending.get_mut(&p).and_then(|rs| rs.pop()).unwrap();
+            moves.push(Move {
+                partition: p,
+                from,
+                to: to.clone(),
+            });
+            ns.push(to);
+            remains.0.insert(p, ns);
+        };
+
+        debug!("groups len: {}", groups.len());
+        while let Some((mut group_key, mut pps)) = groups.pop() {
+            while let Some((p, _ns)) = pps.first() {
+                debug!("======== group_key {:?} ========", group_key);
+                if group_key.is_empty() {
+                    bail!("No remaining node to take the replica of {p}");
+                }
+
+                group_key.sort_by(|(n1, len1), (n2, len2)| {
+                    deviation(n1, *len1).total_cmp(&deviation(n2, *len2))
+                });
+                let upper = group_key.last().map(|(n, len)| deviation(n, *len)).unwrap();
+                let lower = group_key.first().map(|(n, len)| deviation(n, *len)).unwrap();
+
+                if upper == lower {
+                    // cycle
+                    let rest = group_key
+                        .iter()
+                        .map(|(n, _len)| n)
+                        .cycle()
+                        .take(pps.len())
+                        .zip(pps);
+                    for (n, (p, ns)) in rest {
+                        refill(self, p, ns, n.clone());
+                    }
+
+                    break;
+                } else {
+                    let (p, ns) = pps.remove(0);
+                    let (picked_node, picked_node_count) = group_key.first_mut().unwrap();
+                    let picked_node = picked_node.clone();
+                    *picked_node_count += 1;
+                    debug!(">>> pick: {:?}", picked_node);
+                    refill(self, p, ns, picked_node);
+                }
+            }
+            cal_groups(self, &mut groups);
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the `from` node with the `to` node in place, keeping the replica positions, so
+    /// every partition on `from` moves exactly once, straight to `to`.
+    ///
+    /// Returns the new assignment and the moves to get there.
+    pub fn replace_node(mut self, from: &Node, to: &Node) -> Result<(Self, Vec<Move>)> {
+        self.ensure_contains_node(from)?;
+        if self.contains_node(to) {
+            bail!("{to} already exists in the assignment");
+        }
+
+        let mut moves = vec![];
+        for (p, ns) in self.0.iter_mut() {
+            for n in ns.iter_mut().filter(|n| *n == from) {
+                *n = to.clone();
+                moves.push(Move {
+                    partition: *p,
+                    from: from.clone(),
+                    to: to.clone(),
+                });
+            }
+        }
+
+        Ok((self, moves))
+    }
+
+    /// Rebalances the assignment as is, spreading the replicas of each partition across the zones,
+    /// and then moving partitions from the upper bound nodes to the lower bound nodes.
+    ///
+    /// Returns the new assignment and the moves to get there.
+    pub fn rebalance(self, topology: &Topology) -> (Self, Vec<Move>) {
+        let nodes = self.nodes().into_iter().cloned().collect::<Vec<_>>();
+        let (assignment, moves) = self.spread_zones(vec![], &nodes, topology);
+        assignment.balance_boundary(moves, [], topology)
+    }
+
+    /// Checks that the assignment is not empty and has enough nodes for `replication_factor`.
+    pub fn validate(&self, replication_factor: usize) -> Result<()> {
+        if self.0.is_empty() {
+            bail!("Assignment must not be empty");
+        }
+
+        let nodes = self.0.values().flatten().collect::<BTreeSet<_>>();
+        if nodes.len() < replication_factor {
+            bail!("Nodes must be greater than or equal to replication factor");
+        }
+
+        Ok(())
+    }
+
+    /// The partitions whose replicas don't span as many distinct zones of `topology` as
+    /// possible.
+    pub fn zone_violations(&self, topology: &Topology) -> Vec<Partition> {
+        let zones_count = topology.zones(self.nodes()).len();
+        self.0
+            .iter()
+            .filter(|(_p, ns)| !topology.is_spread(ns, zones_count))
+            .map(|(p, _ns)| *p)
+            .collect()
+    }
+
+    /// Whether `node` holds any replica in the assignment.
+    pub fn contains_node(&self, node: &Node) -> bool {
+        self.0.iter().any(|(_p, ns)| ns.contains(node))
+    }
+
+    /// Same as [`Assignment::contains_node`], but returns an error if not contained.
+    pub fn ensure_contains_node(&self, node: &Node) -> Result<()> {
+        if !self.contains_node(node) {
+            bail!("{node} is not contained in the assignment");
+        }
+
+        Ok(())
+    }
+
+    /// Fails if any of `adds` is duplicated or already in the assignment.
+    pub(crate) fn check_adds(&self, adds: &[Node]) -> Result<()> {
+        ensure_unique(adds)?;
+        for node in adds {
+            if self.contains_node(node) {
+                bail!("{node} already exists in the assignment");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The nodes remaining after removing `removes`, which must be distinct nodes of the
+    /// assignment, and leave at least `replication_factor` nodes, and no less than the replication
+    /// factor of the assignment either.
+    pub(crate) fn remaining_nodes(&self, removes: &[Node], replication_factor: usize)
+    -> Result<Vec<Node>>
+    {
+        if removes.is_empty() {
+            bail!("Empty nodes to remove");
+        }
+
+        ensure_unique(removes)?;
+        for remove in removes {
+            self.ensure_contains_node(remove)?;
+        }
+
+        let nodes = self
+            .nodes()
+            .into_iter()
+            .filter(|n| !removes.contains(n))
+            .cloned()
+            .collect::<Vec<_>>();
+        if nodes.len() < replication_factor.max(self.replication_factor()) {
+            bail!("NO less nodes then the replication factor");
+        }
+
+        Ok(nodes)
+    }
+
+    /// The partitions and the nodes holding their replicas.
+    pub fn partitions(&self) -> &BTreeMap<Partition, Vec<Node>> {
+        &self.0
+    }
+
+    /// The nodes holding replicas of `partition`.
+    pub fn get(&self, partition: &Partition) -> Option<&Vec<Node>> {
+        self.0.get(partition)
+    }
+
+    /// The number of partitions.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Whether there is no partition at all.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The id the partitions start with, i.e. 0 if there is partition 0, e.g. the assignments
+    /// initialized with `starts_with_zero`, otherwise 1.
+    ///
+    /// Only the base is inferred, not the smallest id, so a missing first partition is still
+    /// missing, instead of shifting the range.
+    pub fn first_id(&self) -> u32 {
+        if self.0.contains_key(&Partition(0)) { 0 } else { 1 }
+    }
+
+    /// The replication factor, i.e. the most replicas of any partition.
+    pub fn replication_factor(&self) -> usize {
+        self.0.values().map(|ns| ns.len()).max().unwrap_or(0)
+    }
+
+    /// All the nodes holding replicas in the assignment.
+    pub fn nodes(&self) -> BTreeSet<&Node> {
+        self.0.values().flatten().collect()
+    }
+
+    /// The balance targets of the nodes in the assignment, proportional to their weights in
+    /// `topology`.
+    pub fn targets(&self, topology: &Topology) -> BTreeMap<Node, Target> {
+        topology.targets(self.nodes(), self.replicas(), self.0.len())
+    }
+
+    /// Whether moving the replica of `p` from `from` to `to` keeps the zones spread of `p`, i.e.
+    /// it spans no less zones, or still as many as required.
+    pub(crate) fn keeps_spread(
+        &self,
+        p: &Partition,
+        from: &Node,
+        to: &Node,
+        topology: &Topology,
+        zones_count: usize,
+    ) -> bool {
+        let ns = &self.0[p];
+        let replaced = ns.iter().map(|n| if n == from { to } else { n });
+        let spread = topology.zones(replaced).len();
+        spread >= topology.zones(ns).len() || spread >= topology.required_spread(ns, zones_count)
+    }
+
+    /// The total number of replicas of all the partitions.
+    fn replicas(&self) -> usize {
+        self.0.values().map(|ns| ns.len()).sum()
+    }
+
+    /// Transforms the assignment to `Node => Set(partitions)`.
+    pub fn nodes_map(&self) -> HashMap<Node, BTreeSet<Partition>> {
+        let mut nodes_map: HashMap<Node, BTreeSet<Partition>> = Default::default();
+        for (p, ns) in &self.0 {
+            for n in ns {
+                let v = nodes_map.entry(n.clone()).or_default();
+                v.insert(*p);
+            }
+        }
+
+        nodes_map
+    }
+
+    /// Consumes the assignment, returning the inner map.
+    pub fn into_inner(self) -> BTreeMap<Partition, Vec<Node>> {
+        self.0
+    }
+
+    /// Moves replicas to the zones not covered yet, until the replicas of every partition span
+    /// as many distinct zones of `topology` as possible.
+    fn spread_zones(mut self, mut moves: Vec<Move>, nodes: &[Node], topology: &Topology)
+    -> (Self, Vec<Move>)
+    {
+        let zones_count = topology.zones(nodes).len();
+        let targets = topology.targets(nodes, self.replicas(), self.0.len());
+        let mut nodes_map = self.nodes_map();
+        for n in nodes {
+            nodes_map.entry(n.clone()).or_default();
+        }
+        let deviation = |n: &Node, ps: &BTreeSet<Partition>| ps.len() as f64 - targets[n].ideal;
+
+        for (p, ns) in self.0.iter_mut() {
+            while !topology.is_spread(ns, zones_count) {
+                let zones = topology.zones(ns.iter());
+
+                // The least loaded node in a zone the partition is not in yet
+                let Some(to) = nodes
+                    .iter()
+                    .filter(|&n| !zones.contains(topology.zone(n)))
+                    .min_by(|&n1, &n2| {
+                        deviation(n1, &nodes_map[n1]).total_cmp(&deviation(n2, &nodes_map[n2]))
+                    })
+                    .cloned()
+                else {
+                    break;
+                };
+
+                // The most loaded replica in the most crowded zone of the partition
+                let crowd = |n: &Node| {
+                    ns.iter().filter(|&m| topology.zone(m) == topology.zone(n)).count()
+                };
+                let from = ns
+                    .iter()
+                    .max_by(|&n1, &n2| {
+                        crowd(n1).cmp(&crowd(n2)).then_with(|| {
+                            deviation(n1, &nodes_map[n1]).total_cmp(&deviation(n2, &nodes_map[n2]))
+                        })
+                    })
+                    .cloned()
+                    .unwrap();
+
+                debug!("Spread {p} from {from} to {to} for zones");
+                for n in ns.iter_mut() {
+                    if *n == from {
+                        *n = to.clone();
+                    }
+                }
+                nodes_map.get_mut(&from).unwrap().remove(p);
+                nodes_map.get_mut(&to).unwrap().insert(*p);
+                moves.push(Move { partition: *p, from, to });
+            }
+        }
+
+        (self, moves)
+    }
+
+    /// Moves partitions from the upper bound nodes to the lower bound nodes, until
+    /// `upper bound - lower bound <= 1`, without breaking the zones spread of any partition.
+    fn balance_boundary<I>(mut self, mut moves: Vec<Move>, adds: I, topology: &Topology)
+    -> (Self, Vec<Move>)
+    where
+        I: IntoIterator<Item = Node>,
+    {
+        let mut nodes_map = self.nodes_map();
+        for add in adds {
+            nodes_map.entry(add).or_default();
+        }
+
+        if nodes_map.len() <= 1 {
+            return (self, moves);
+        }
+
+        let zones_count = topology.zones(nodes_map.keys()).len();
+        let targets = topology.targets(nodes_map.keys(), self.replicas(), self.0.len());
+        let deviation = |n: &Node, ps: &BTreeSet<Partition>| ps.len() as f64 - targets[n].ideal;
+
+        loop {
+            // Sort by how far from the ideal number of partitions, which is just the number of
+            // partitions when all the nodes weigh the same
+            let mut nodes = nodes_map.iter().collect::<Vec<_>>();
+            // The ties are broken by the names, so the result doesn't depend on the hash map order
+            nodes.sort_by(|(n1, ps1), (n2, ps2)| {
+                deviation(n1, ps1).total_cmp(&deviation(n2, ps2)).then_with(|| n1.cmp(n2))
+            });
+
+            // Find a partition on the upper bound node but the lower bound node doesn't have,
+            // which is also allowed by the zones to be moved. Try the next pair of the bound
+            // nodes if not found.
+            let found = nodes.iter().rev().find_map(|&(upper, ups)| {
+                nodes
+                    .iter()
+                    .take_while(|(lower, lps)| deviation(upper, ups) - deviation(lower, lps) > 1.0)
+                    .filter(|(lower, lps)| {
+                        ups.len() > targets[upper].upper || lps.len() < targets[lower].lower
+                    })
+                    .find_map(|&(lower, lps)| {
+                        ups.difference(lps)
+                            .find(|p| self.keeps_spread(p, upper, lower, topology, zones_count))
+                            .map(|p| (*p, upper.clone(), lower.clone()))
+                    })
+            });
+
+            let Some((p, upper, lower)) = found else {
+                break;
+            };
+
+            // move p from upper to lower
+            debug!("Move {p} from upper bound node {} to lower bound node {}", upper.0, lower.0);
+
+            if let Some(ns) = self.0.get_mut(&p) {
+                for n in ns.iter_mut() {
+                    if *n == upper {
+                        // swich partition p from upper bound node to lower bound node
+                        *n = lower.clone();
+                    }
+                }
+            }
+            nodes_map.get_mut(&upper).unwrap().remove(&p);
+            nodes_map.get_mut(&lower).unwrap().insert(p);
+            moves.push(Move {
+                partition: p,
+                from: upper,
+                to: lower,
+            });
+        }
+
+        (self, moves)
+    }
+}
@@ -1,19 +1,14 @@
-use std::num::{NonZeroU32, NonZeroU8};
-use std::collections::{BTreeMap, BTreeSet, HashMap};
-use clap::{Parser, Subcommand};
+use std::num::{NonZeroU32, NonZeroU8, NonZeroUsize};
+use std::collections::BTreeMap;
+use clap::{Args, Parser, Subcommand};
 use clap_stdin::FileOrStdin;
 use serde::{Deserialize, Serialize};
-use log::debug;
-use anyhow::{anyhow, bail, Error, Result};
-
-#[derive(Debug, Clone, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
-struct Node(String);
-
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
-struct Partition(u32);
-
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
-struct Assignment(BTreeMap<Partition, Vec<Node>>);
+use anyhow::{anyhow, bail, Result};
+use assignment::{
+    atom, batches, mnesia_script, Action, Assignment, Cost, Document, GreedyStrategy,
+    KafkaReassignment, MnesiaSnapshot, Move, Node, NodePolicy, OptimalStrategy, Partition, Plan,
+    RendezvousStrategy, Report, Severity, Sizes, Strategy, Topology, Violation,
+};
 
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
@@ -21,12 +16,86 @@ struct Assignment(BTreeMap<Partition, Vec<Node>>);
 struct Cli {
     #[command(subcommand)]
     command: Command,
+
+    /// Trim the whitespaces around the node names
+    #[arg(long, global = true, default_value = "false")]
+    trim_node_names: bool,
+
+    /// Fold the node names to lowercase
+    #[arg(long, global = true, default_value = "false")]
+    lowercase_node_names: bool,
+
+    /// The regex the whole node name must match after trimmed and folded, e.g. `[a-z0-9_.@-]+`
+    #[arg(long, global = true, value_name = "REGEX")]
+    node_name_pattern: Option<String>,
 }
 
 #[derive(Debug, Clone, Deserialize, clap::ValueEnum)]
 enum OutputFormat {
     Json,
     Text,
+    /// The Erlang term list in the shape of `frag_dist`
+    Erlang,
+    /// The `mnesia:add_table_copy/3` and `mnesia:del_table_copy/2` calls to apply the plan
+    MnesiaScript,
+    /// The partition reassignment JSON of `kafka-reassign-partitions`
+    Kafka,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum BalanceBy {
+    Count,
+    Bytes,
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum StrategyKind {
+    /// Spread the zones and move partitions between the bound nodes step by step
+    Greedy,
+    /// Solve the minimum number of moves for a balanced result as a min-cost flow problem
+    Optimal,
+    /// Rendezvous (highest random weight) hashing, which only depends on the nodes
+    Rendezvous,
+}
+
+impl StrategyKind {
+    fn strategy(&self) -> Box<dyn Strategy> {
+        match self {
+            Self::Greedy => Box::new(GreedyStrategy),
+            Self::Optimal => Box::new(OptimalStrategy),
+            Self::Rendezvous => Box::new(RendezvousStrategy),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+enum StorageType {
+    #[value(name = "ram_copies")]
+    Ram,
+    #[value(name = "disc_copies")]
+    Disc,
+    #[value(name = "disc_only_copies")]
+    DiscOnly,
+}
+
+impl StorageType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ram => "ram_copies",
+            Self::Disc => "disc_copies",
+            Self::DiscOnly => "disc_only_copies",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, clap::ValueEnum)]
+enum InputFormat {
+    /// The assignment JSON, e.g. `{"1": ["n1", "n2"], "2": ["n2", "n3"]}`
+    Json,
+    /// The Erlang/Mnesia JSON dump with `.current_snapshot.table_distribution[].frag_dist`
+    MnesiaSnapshot,
+    /// The partition reassignment JSON of `kafka-reassign-partitions`
+    Kafka,
 }
 
 #[derive(Debug, Subcommand)]
@@ -56,6 +125,26 @@ enum Command {
         /// Partitions start with 0 or 1
         #[arg(short = '0', long, default_value = "false")]
         starts_with_zero: bool,
+
+        /// The table or topic name, required by the `mnesia-script` and `kafka` output formats
+        #[arg(long, visible_alias = "topic")]
+        table: Option<String>,
+
+        /// The names of many tables or topics to initialize on the same nodes at once, in
+        /// comma-separated format, balancing the total number of replicas on each node as well
+        #[arg(long, value_delimiter = ',', conflicts_with = "table")]
+        tables: Vec<String>,
+
+        /// Whether to balance the leaders (first replicas) of the partitions across the nodes
+        #[arg(short = 'l', long, default_value = "false")]
+        balance_leaders: bool,
+
+        #[command(flatten)]
+        topology: TopologyArgs,
+
+        /// The strategy to place the partitions
+        #[arg(long, value_enum, default_value_t = StrategyKind::Greedy, conflicts_with = "tables")]
+        strategy: StrategyKind,
     },
 
     /// Add a node or multiple nodes to the assignment, and reassign partitions
@@ -64,36 +153,155 @@ enum Command {
         #[arg(short, long, value_delimiter = ',')]
         nodes: Vec<Node>,
 
-        /// The existing assignment file, "-" means reading from STDIN
-        #[arg(short, long, default_value = "-")]
-        input: FileOrStdin<Assignment>,
+        /// Whether to balance the leaders (first replicas) of the partitions across the nodes
+        #[arg(short = 'l', long, default_value = "false")]
+        balance_leaders: bool,
 
-        /// Whether to include the actions list in the JSON output
-        #[arg(short, long, default_value = "false")]
-        with_actions: bool,
+        #[command(flatten)]
+        topology: TopologyArgs,
 
-        /// The output format
-        #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
-        output_format: OutputFormat,
+        #[command(flatten)]
+        input: InputArgs,
+
+        #[command(flatten)]
+        reassign: ReassignArgs,
+
+        #[command(flatten)]
+        plan: PlanArgs,
     },
 
-    /// Remove a node from the assignment, and reassign partitions
+    /// Remove a node or multiple nodes from the assignment, and reassign partitions
     Remove {
-        /// The node to remove
-        #[arg(short, long)]
-        node: Node,
+        /// Nodes to remove, in comma-separated format
+        #[arg(short, long = "nodes", alias = "node", value_delimiter = ',', required = true)]
+        nodes: Vec<Node>,
 
-        /// The replication factor
+        /// Whether to balance the leaders (first replicas) of the partitions across the nodes
+        #[arg(short = 'l', long, default_value = "false")]
+        balance_leaders: bool,
+
+        #[command(flatten)]
+        topology: TopologyArgs,
+
+        /// The replication factor, or the replication factor of each table with `--all-tables`
+        #[arg(short, long, required_unless_present = "all_tables")]
+        replication_factor: Option<NonZeroU8>,
+
+        #[command(flatten)]
+        input: InputArgs,
+
+        #[command(flatten)]
+        reassign: ReassignArgs,
+
+        #[command(flatten)]
+        plan: PlanArgs,
+    },
+
+    /// Rebalance the assignment as is, e.g. after changing the topology
+    Rebalance {
+        /// Whether to balance the leaders (first replicas) of the partitions across the nodes
+        #[arg(short = 'l', long, default_value = "false")]
+        balance_leaders: bool,
+
+        #[command(flatten)]
+        topology: TopologyArgs,
+
+        #[command(flatten)]
+        input: InputArgs,
+
+        #[command(flatten)]
+        reassign: ReassignArgs,
+
+        #[command(flatten)]
+        plan: PlanArgs,
+    },
+
+    /// Replace a node with another one in place, moving each of its partitions once
+    Replace {
+        /// The node to replace
+        #[arg(long)]
+        from: Node,
+
+        /// The node to replace with, which must not be in the assignment yet
+        #[arg(long)]
+        to: Node,
+
+        #[command(flatten)]
+        input: InputArgs,
+
+        #[command(flatten)]
+        plan: PlanArgs,
+    },
+
+    /// Change the replication factor of the assignment, adding or dropping replicas
+    SetReplicationFactor {
+        /// The new replication factor
         #[arg(short, long)]
         replication_factor: NonZeroU8,
 
-        /// The existing assignment file, "-" means reading from STDIN
+        /// Whether to balance the leaders (first replicas) of the partitions across the nodes
+        #[arg(short = 'l', long, default_value = "false")]
+        balance_leaders: bool,
+
+        #[command(flatten)]
+        topology: TopologyArgs,
+
+        #[command(flatten)]
+        input: InputArgs,
+
+        #[command(flatten)]
+        plan: PlanArgs,
+    },
+
+    /// Append partitions after the current maximum partition, and reassign partitions
+    AddPartitions {
+        /// The number of partitions to add
+        #[arg(short, long)]
+        count: NonZeroU32,
+
+        /// Whether to balance the leaders (first replicas) of the partitions across the nodes
+        #[arg(short = 'l', long, default_value = "false")]
+        balance_leaders: bool,
+
+        #[command(flatten)]
+        topology: TopologyArgs,
+
+        #[command(flatten)]
+        input: InputArgs,
+
+        #[command(flatten)]
+        plan: PlanArgs,
+    },
+
+    /// Delete the trailing partitions, and reassign partitions
+    RemovePartitions {
+        /// The number of partitions to remove
+        #[arg(short, long)]
+        count: NonZeroU32,
+
+        /// Whether to balance the leaders (first replicas) of the partitions across the nodes
+        #[arg(short = 'l', long, default_value = "false")]
+        balance_leaders: bool,
+
+        #[command(flatten)]
+        topology: TopologyArgs,
+
+        #[command(flatten)]
+        input: InputArgs,
+
+        #[command(flatten)]
+        plan: PlanArgs,
+    },
+
+    /// List the tables in an Erlang/Mnesia JSON dump, or the topics in a Kafka reassignment JSON
+    Tables {
+        /// The Erlang/Mnesia JSON dump or Kafka reassignment JSON file, "-" means reading from STDIN
         #[arg(short, long, default_value = "-")]
-        input: FileOrStdin<Assignment>,
+        input: FileOrStdin<String>,
 
-        /// Whether to include the actions list in the JSON output
-        #[arg(short, long, default_value = "false")]
-        with_actions: bool,
+        /// The format of the input
+        #[arg(long, value_enum, default_value_t = InputFormat::MnesiaSnapshot)]
+        input_format: InputFormat,
 
         /// The output format
         #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
@@ -102,17 +310,52 @@ enum Command {
 
     /// Validate the assignment
     Validate {
-        /// The number of partitions
-        #[arg(short, long)]
-        partitions: NonZeroU32,
+        /// The number of partitions, or the number of partitions of each table with `--all-tables`
+        #[arg(short, long, required_unless_present = "all_tables")]
+        partitions: Option<NonZeroU32>,
 
-        /// The replication factor
-        #[arg(short, long)]
-        replication_factor: NonZeroU8,
+        /// Partitions start with 0 or 1, inferred from whether there is partition 0 if neither
+        /// this nor `--first-id` provided
+        #[arg(short = '0', long, default_value = "false")]
+        starts_with_zero: bool,
+
+        /// The first partition id, for the partitions of an arbitrary id range
+        #[arg(long, conflicts_with = "starts_with_zero")]
+        first_id: Option<u32>,
+
+        /// Whether to check the leaders (first replicas) of the partitions are balanced
+        #[arg(short = 'l', long, default_value = "false")]
+        check_leaders: bool,
+
+        #[command(flatten)]
+        topology: TopologyArgs,
+
+        /// The replication factor, or the replication factor of each table with `--all-tables`
+        #[arg(short, long, required_unless_present = "all_tables")]
+        replication_factor: Option<NonZeroU8>,
 
         /// The existing assignment file, "-" means reading from STDIN
         #[arg(short, long, default_value = "-")]
-        input: FileOrStdin<Assignment>,
+        input: FileOrStdin<String>,
+
+        /// The format of the input
+        #[arg(long, value_enum, default_value_t = InputFormat::Json)]
+        input_format: InputFormat,
+
+        /// The table or topic to read from the input, required by the `mnesia-snapshot` input
+        /// format and the `kafka` output format
+        #[arg(long, visible_alias = "topic")]
+        table: Option<String>,
+
+        /// Whether the input holds many tables or topics, to work on all of them at once, e.g. a
+        /// JSON document of the assignments by table, `{"t1": {"1": ["n1"]}, "t2": {...}}`
+        #[arg(long, default_value = "false")]
+        all_tables: bool,
+
+        /// Whether to check the total number of replicas on each node across all the tables are
+        /// balanced, with `--all-tables`
+        #[arg(long, default_value = "false", requires = "all_tables")]
+        check_totals: bool,
 
         /// The output format
         #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
@@ -120,46 +363,339 @@ enum Command {
     },
 }
 
+/// The existing assignment to work on.
+#[derive(Debug, Args)]
+struct InputArgs {
+    /// The existing assignment file, "-" means reading from STDIN
+    #[arg(short, long, default_value = "-")]
+    input: FileOrStdin<String>,
+
+    /// The format of the input
+    #[arg(long, value_enum, default_value_t = InputFormat::Json)]
+    input_format: InputFormat,
+
+    /// The table or topic to read from the input, required by the `mnesia-snapshot` input
+    /// format and the `mnesia-script` and `kafka` output formats
+    #[arg(long, visible_alias = "topic")]
+    table: Option<String>,
+
+    /// The storage type of the table copies added by the `mnesia-script` output format
+    #[arg(long, value_enum, default_value_t = StorageType::Disc)]
+    storage_type: StorageType,
+}
+
+/// The zone and weight of each node.
+#[derive(Debug, Args)]
+struct TopologyArgs {
+    /// The topology file of the zone and weight of each node, e.g. `{"n1": {"zone": "az1", "weight": 2}}`
+    #[arg(short, long)]
+    topology: Option<FileOrStdin<Topology>>,
+
+    /// The weights of nodes, in comma-separated `node=weight` format, overriding the topology
+    #[arg(long, value_delimiter = ',', value_parser = parse_weight)]
+    weights: Vec<(Node, u32)>,
+}
+
+/// How to reassign the partitions of the nodes added or removed, or to rebalance, and the limits
+/// of the moves of each batch.
+#[derive(Debug, Args)]
+struct ReassignArgs {
+    /// Whether the input holds many tables or topics, to work on all of them at once, e.g. a
+    /// JSON document of the assignments by table, `{"t1": {"1": ["n1"]}, "t2": {...}}`
+    #[arg(long, default_value = "false")]
+    all_tables: bool,
+
+    /// Whether to balance the total number of replicas on each node across all the tables,
+    /// with `--all-tables`
+    #[arg(long, default_value = "false", requires = "all_tables")]
+    balance_totals: bool,
+
+    /// The size file of each partition in bytes, e.g. `{"1": 10485760, "2": 42949672960}`, to
+    /// report the bytes to move
+    #[arg(long, conflicts_with = "all_tables")]
+    sizes: Option<FileOrStdin<Sizes>>,
+
+    /// Balance the number of partitions, or the bytes of the partitions with `--sizes`
+    #[arg(long, value_enum, default_value_t = BalanceBy::Count)]
+    balance_by: BalanceBy,
+
+    /// The strategy to reassign the partitions
+    #[arg(long, value_enum, default_value_t = StrategyKind::Greedy, conflicts_with = "all_tables")]
+    strategy: StrategyKind,
+
+    /// The maximum number of moves to any one node in each batch
+    #[arg(long)]
+    max_inbound_per_node: Option<NonZeroUsize>,
+
+    /// The maximum number of moves from any one node in each batch
+    #[arg(long)]
+    max_outbound_per_node: Option<NonZeroUsize>,
+}
+
+/// The output of the new assignment and the plan to get there.
+#[derive(Debug, Args)]
+struct PlanArgs {
+    /// Whether to include the actions list in the JSON output
+    #[arg(short, long, default_value = "false")]
+    with_actions: bool,
+
+    /// The maximum number of concurrent steps touching any one node in each phase of the plan
+    #[arg(long)]
+    max_concurrent_per_node: Option<NonZeroUsize>,
+
+    /// The output format
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    output_format: OutputFormat,
+}
+
 #[derive(Debug, Default, Serialize)]
 struct Output {
     assignment: Assignment,
 
-    moves: Vec<Move>,
+    actions: Vec<Action>,
+
+    actions_count: usize,
+
+    counts: BTreeMap<&'static str, usize>,
+
+    plan: Plan,
+
+    batches: Vec<Vec<Move>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<Bytes>,
+
+    #[serde(skip)]
+    throttled: bool,
+
+    #[serde(skip)]
+    table: Option<(String, StorageType)>,
+}
+
+impl Output {
+    fn new(assignment: Assignment, actions: Vec<Action>, max_per_node: Option<NonZeroUsize>) -> Self {
+        Self {
+            assignment,
+            plan: Plan::new(&actions, max_per_node.map(|n| n.get())),
+            batches: batches(&moves_of(&actions), None, None),
+            bytes: None,
+            throttled: false,
+            table: None,
+            actions_count: actions.len(),
+            counts: Action::counts(&actions),
+            actions,
+        }
+    }
+
+    /// Splits the moves into batches with the per-node inbound and outbound limits.
+    fn throttle(mut self, max_inbound: Option<NonZeroUsize>, max_outbound: Option<NonZeroUsize>)
+    -> Self
+    {
+        self.throttled = max_inbound.is_some() || max_outbound.is_some();
+        self.batches = batches(
+            &moves_of(&self.actions),
+            max_inbound.map(|n| n.get()),
+            max_outbound.map(|n| n.get()),
+        );
+        self
+    }
+
+    /// Reports the bytes to move, and the bytes on each node with `sizes`.
+    fn sizes(mut self, sizes: Option<&Sizes>) -> Self {
+        self.bytes = sizes.map(|sizes| Bytes {
+            cost: sizes.cost(&moves_of(&self.actions)),
+            nodes: sizes.node_bytes(&self.assignment),
+        });
+        self
+    }
+
+    /// Sets the table and the storage type of the `mnesia-script` output.
+    fn table(mut self, table: Option<String>, storage_type: StorageType) -> Self {
+        self.table = table.map(|t| (t, storage_type));
+        self
+    }
+
+    fn print(self, title: &str, notes: &[String], output_format: OutputFormat, with_actions: bool)
+    -> Result<()>
+    {
+        match output_format {
+            OutputFormat::Json => {
+                if with_actions {
+                    println!("{}", serde_json::to_string_pretty(&self)?);
+                } else {
+                    println!("{}", serde_json::to_string_pretty(&self.assignment)?);
+                }
+            }
+            OutputFormat::Text => {
+                println!("==== {title}, Assignment: ====");
+                print_assignment(&self.assignment);
+                let counts = self.counts
+                    .iter()
+                    .map(|(kind, count)| format!("{kind}: {count}"))
+                    .collect::<Vec<_>>().join(", ");
+                println!("Actions: {}, [{counts}]", self.actions_count);
+                for note in notes {
+                    println!("{note}");
+                }
+                print_actions(&self.actions);
+                print_plan(&self.plan);
+                if self.throttled {
+                    print_batches(&self.batches);
+                }
+                if let Some(bytes) = &self.bytes {
+                    print_bytes(bytes);
+                }
+            }
+            OutputFormat::Erlang => {
+                println!("{}", self.assignment.to_erlang());
+            }
+            OutputFormat::MnesiaScript => {
+                let Some((table, storage_type)) = &self.table else {
+                    bail!("`--table` is required for the mnesia-script output");
+                };
+                let first_id = self.assignment.first_id();
+                let script =
+                    mnesia_script(table, storage_type.as_str(), first_id, &self.actions, &self.plan);
+                println!("{script}");
+            }
+            OutputFormat::Kafka => {
+                let Some((topic, _)) = &self.table else {
+                    bail!("`--topic` is required for the kafka output");
+                };
+                println!("{}", KafkaReassignment::to_json([(topic.as_str(), &self.assignment)])?);
+            }
+        }
 
-    moves_count: usize,
+        Ok(())
+    }
 }
 
-#[derive(Debug, Clone, Serialize)]
-struct Move {
-    partition: Partition,
-    from: Node,
-    to: Node,
+#[derive(Debug, Serialize)]
+struct ReportOutput<'a> {
+    valid: bool,
+
+    errors: usize,
+
+    warnings: usize,
+
+    violations: &'a [Violation],
 }
 
-fn main() -> Result<()> {
-    pretty_env_logger::init();
-    let cli = Cli::parse();
-    cli.command.exec()?;
+#[derive(Debug, Default, Serialize)]
+struct Bytes {
+    cost: Cost,
 
-    Ok(())
+    nodes: BTreeMap<Node, u64>,
 }
 
-type GroupKey = Vec<(Node, usize)>;
-type GroupValue = Vec<(Partition, Vec<Node>)>;
-type Groups = Vec<(GroupKey, GroupValue)>;
+#[derive(Debug, Default, Serialize)]
+struct DocumentOutput {
+    tables: BTreeMap<String, Output>,
+
+    totals: BTreeMap<Node, usize>,
+}
 
-fn cal_groups(assignment: &Assignment, groups: &mut Groups) {
-    let mut remains_nodes: BTreeMap<Node, Vec<_>> = Default::default();
-    for (p, ns) in &assignment.0 {
-        for n in ns {
-            let v = remains_nodes.entry(n.clone()).or_default();
-            v.push(*p);
+impl DocumentOutput {
+    fn new(
+        document: Document,
+        mut moves: BTreeMap<String, Vec<Move>>,
+        topology: &Topology,
+        leaders: bool,
+        max_per_node: Option<NonZeroUsize>,
+        storage_type: StorageType,
+    ) -> Self {
+        let totals = document.totals();
+        let tables = document
+            .into_inner()
+            .into_iter()
+            .map(|(name, assignment)| {
+                let (assignment, promotions) = balance_leaders(assignment, topology, leaders);
+                let actions = moves
+                    .remove(&name)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(Action::from)
+                    .chain(promotions)
+                    .collect();
+                let output = Output::new(assignment, actions, max_per_node)
+                    .table(Some(name.clone()), storage_type);
+                (name, output)
+            })
+            .collect();
+
+        Self { tables, totals }
+    }
+
+    /// Splits the moves of every table into batches with the per-node inbound and outbound limits.
+    fn throttle(mut self, max_inbound: Option<NonZeroUsize>, max_outbound: Option<NonZeroUsize>)
+    -> Self
+    {
+        self.tables = self.tables
+            .into_iter()
+            .map(|(name, output)| (name, output.throttle(max_inbound, max_outbound)))
+            .collect();
+        self
+    }
+
+    fn print(self, title: &str, notes: &[String], output_format: OutputFormat, with_actions: bool)
+    -> Result<()>
+    {
+        match output_format {
+            OutputFormat::Json => {
+                if with_actions {
+                    println!("{}", serde_json::to_string_pretty(&self)?);
+                } else {
+                    let document = self.tables
+                        .into_iter()
+                        .map(|(name, output)| (name, output.assignment))
+                        .collect::<Document>();
+                    println!("{}", serde_json::to_string_pretty(&document)?);
+                }
+            }
+            OutputFormat::Text => {
+                for note in notes {
+                    println!("{note}");
+                }
+                for (name, output) in self.tables {
+                    output.print(&format!("{title}, Table: {name}"), &[], OutputFormat::Text, with_actions)?;
+                }
+                print_totals(&self.totals);
+            }
+            OutputFormat::Erlang | OutputFormat::MnesiaScript => {
+                for (name, output) in self.tables {
+                    println!("%% Table: {name}");
+                    output.print(title, &[], output_format.clone(), with_actions)?;
+                }
+            }
+            OutputFormat::Kafka => {
+                println!("{}", KafkaReassignment::to_json(
+                    self.tables.iter().map(|(t, output)| (t.as_str(), &output.assignment)),
+                )?);
+            }
         }
+
+        Ok(())
     }
+}
+
+fn main() -> Result<()> {
+    pretty_env_logger::init();
+    let cli = Cli::parse();
+    let policy = cli.policy()?;
+    cli.command.exec(&policy)?;
 
-    for (group, _pps) in groups.iter_mut() {
-        for (node, len) in group.iter_mut() {
-            *len = remains_nodes.get(node).map(|ps| ps.len()).unwrap();
+    Ok(())
+}
+
+impl Cli {
+    /// The normalization policy of the node names from the CLI and the input.
+    fn policy(&self) -> Result<NodePolicy> {
+        let policy = NodePolicy::new()
+            .with_trim(self.trim_node_names)
+            .with_fold_case(self.lowercase_node_names);
+        match &self.node_name_pattern {
+            Some(pattern) => policy.with_pattern(pattern),
+            None => Ok(policy),
         }
     }
 }
@@ -176,12 +712,16 @@ fn print_partitions<'a, I>(partitions: I, prefix: Option<&str>)
     println!("{prefix}----------\t---------");
     for (p, ns) in partitions.clone() {
         println!("{prefix}       {:>2}\t{}",
-        p.0,
-        ns.iter().map(|n| format!("{:>2}", n.0)).collect::<Vec<_>>().join(", "));
+        p.id(),
+        ns.iter().map(|n| format!("{:>2}", n.as_str())).collect::<Vec<_>>().join(", "));
     }
 
     let mut nodes: BTreeMap<&Node, Vec<&Partition>> = Default::default();
+    let mut leaders: BTreeMap<&Node, usize> = Default::default();
     for (p, ns) in partitions {
+        if let Some(n) = ns.first() {
+            *leaders.entry(n).or_default() += 1;
+        }
         for node in ns {
             let v = nodes.entry(node).or_default();
             v.push(p);
@@ -189,8 +729,8 @@ fn print_partitions<'a, I>(partitions: I, prefix: Option<&str>)
         }
     }
 
-    println!("{prefix}\n{prefix}Node\t Num\tPartitions");
-    println!("{prefix}----\t----\t----------");
+    println!("{prefix}\n{prefix}Node\t Num\tLead\tPartitions");
+    println!("{prefix}----\t----\t----\t----------");
     for (n, ps) in &nodes {
         let ps_len = ps.len();
         if ps_len > upper {
@@ -200,526 +740,758 @@ fn print_partitions<'a, I>(partitions: I, prefix: Option<&str>)
             lower = ps_len;
         }
 
-        println!("{prefix}{:>4}\t{:>4}\t{}",
-          n.0,
+        println!("{prefix}{:>4}\t{:>4}\t{:>4}\t{}",
+          n.as_str(),
           ps.len(),
+          leaders.get(n).unwrap_or(&0),
           ps.iter()
-            .map(|p| format!("{:>2}", p.0)).collect::<Vec<_>>().join(", "));
+            .map(|p| format!("{:>2}", p.id())).collect::<Vec<_>>().join(", "));
     }
 
     println!("{prefix}");
-    println!("{prefix}upper: {upper}, lower: {lower}, Differ: {}", upper - lower);
+    // No bounds without any replica, e.g. an invalid input to validate
+    if !nodes.is_empty() {
+        println!("{prefix}upper: {upper}, lower: {lower}, Differ: {}", upper - lower);
+    }
 }
 
-fn print_moves(moves: &[Move]) {
-    println!("==== Moves: ====");
-    let mut sorted = moves.iter().collect::<Vec<_>>();
-    sorted.sort_by(|m1, m2| {
-        m1.partition.cmp(&m2.partition)
-    });
-    for m in sorted {
-        println!("Move {} from {} to {}", m.partition, m.from, m.to);
+fn parse_weight(s: &str) -> Result<(Node, u32)> {
+    let (node, weight) = s
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Invalid weight `{s}`, expected `node=weight`"))?;
+    let weight = weight.parse::<u32>()?;
+    if weight == 0 {
+        bail!("The weight of {node} must not be zero");
     }
+    Ok((Node::from(node), weight))
 }
 
-impl Command {
-    fn exec(self) -> Result<()> {
-        match self {
-            Self::Init {
-                partitions,
-                replication_factor,
-                mut nodes,
-                output_format,
-                with_actions,
-                starts_with_zero,
-            } => {
-                let replication_factor = replication_factor.get() as usize;
-                if partitions.get() == 0 {
-                    bail!("Partitions must not be zero");
-                }
-
-                if nodes.is_empty() {
-                    bail!("Nodes must not be empty");
-                }
-
-                let nodes_num = nodes.len();
-                nodes.dedup();
-                if nodes.len() != nodes_num {
-                    bail!("Provided duplicated nodes");
-                }
-
-                if nodes_num < replication_factor {
-                    bail!("Nodes must be greater than or equal to replication factor");
-                }
-
-                let assignment = Assignment::init(
-                    &nodes[..],
-                    partitions.get() as usize,
-                    replication_factor,
-                    starts_with_zero,
-                );
-
-                match output_format {
-                    OutputFormat::Json => {
-                        if with_actions {
-                            let out = Output { assignment, ..Default::default() };
-                            println!("{}", serde_json::to_string_pretty(&out)?);
-                        } else {
-                            println!("{}", serde_json::to_string_pretty(&assignment)?);
-                        }
-                    }
-                    OutputFormat::Text => {
-                        println!("==== Initialized Assignment: ====");
-                        assignment.print();
-                    }
-                }
-            }
-            Self::Add { mut nodes, input, output_format, with_actions } => {
-                let assignment = input.contents()?;
-                nodes.retain(|n| !n.0.is_empty());
-                if nodes.is_empty() {
-                    bail!("Empty nodes to add");
-                }
-                let nodes_num = nodes.len();
-                nodes.dedup();
-                if nodes.len() != nodes_num {
-                    bail!("Provided duplicated nodes");
-                }
-
-                for node in nodes.clone() {
-                    if assignment.nodes_map().get(&node).is_some() {
-                        bail!("{node} already exists in the assignment");
-                    }
-                }
-
-                let (assignment, moves) = assignment.add_nodes(nodes.clone())?;
-
-                match output_format {
-                    OutputFormat::Json => {
-                        if with_actions {
-                            let out = Output {
-                                assignment,
-                                moves_count: moves.len(),
-                                moves,
-                            };
-                            println!("{}", serde_json::to_string_pretty(&out)?);
-                        } else {
-                            println!("{}", serde_json::to_string_pretty(&assignment)?);
-                        }
-                    }
-                    OutputFormat::Text => {
-                        println!("==== After add nodes: {}, Assignment: ====",
-                        &nodes.iter().map(|n| n.0.as_str()).collect::<Vec<_>>().join(","));
-                        assignment.print();
-                        println!("Moves: {}", moves.len());
-                        print_moves(&moves);
-                    }
-                }
-            }
-            Self::Remove { node, input, replication_factor, with_actions, output_format } => {
-                let assignment = input.contents()?;
-                let replication_factor = replication_factor.get() as usize;
-                assignment.validate(replication_factor)?;
-                assignment.ensure_contains_node(&node)?;
+fn read_topology(
+    topology: Option<FileOrStdin<Topology>>,
+    weights: Vec<(Node, u32)>,
+    policy: &NodePolicy,
+) -> Result<Topology> {
+    let mut topology = match topology {
+        Some(topology) => topology.contents()?.normalize(policy)?,
+        None => Topology::default(),
+    };
+    for (node, weight) in weights {
+        topology.set_weight(policy.normalize(&node)?, weight);
+    }
+    Ok(topology)
+}
 
-                let partitions_on_remove = assignment.nodes_map().remove(&node).unwrap();
-                let (assignment, moves) = assignment.remove_node(&node, replication_factor)?;
-                let moves_count = moves.len();
-                match output_format {
-                    OutputFormat::Json => {
-                        if with_actions {
-                            let out = Output {
-                                assignment,
-                                moves_count,
-                                moves,
-                            };
-                            println!("{}", serde_json::to_string_pretty(&out)?);
-                        } else {
-                            println!("{}", serde_json::to_string_pretty(&assignment)?);
-                        }
-                    }
-                    OutputFormat::Text => {
-                        println!("==== After remove node: {}, Assignment: ====", &node.0);
-                        assignment.print();
-                        println!("Moves: {}", moves_count);
-                        let s = partitions_on_remove
-                            .iter()
-                            .map(|p| format!("{}", p.0))
-                            .collect::<Vec<_>>().join(", ");
-                        println!("Removed node: {node}, partitions: [{s}]");
-                        print_moves(&moves);
-                    }
-                }
-            }
-            Self::Validate { input, partitions, replication_factor, output_format } => {
-                let partitions = partitions.get() as usize;
-                let factor = replication_factor.get() as usize;
-                let assignment = input.contents()?;
-
-                for p in (1..=partitions as u32).map(From::from) {
-                    assignment.0.get(&p).ok_or_else(|| {
-                        anyhow!("{p} is missing")
-                    })
-                    .and_then(|ns| {
-                        let nodes_num = ns.len();
-                        if nodes_num != factor {
-                            bail!("{p} replicas on {nodes_num} nodes, \
-                            but replication factor is {factor}");
-                        }
-                        let mut ns1 = ns.clone();
-                        ns1.dedup();
+/// Reads the assignment of `table`, which is returned too, as the topic of a Kafka input of a
+/// single topic if not given.
+fn read_assignment(
+    input: FileOrStdin<String>,
+    input_format: InputFormat,
+    table: Option<String>,
+    policy: &NodePolicy,
+) -> Result<(Assignment, Option<String>)> {
+    let contents = input.contents()?;
+    let (assignment, table) = match input_format {
+        InputFormat::Json => (contents.parse()?, table),
+        InputFormat::MnesiaSnapshot => {
+            let name = table.as_deref().ok_or_else(|| anyhow!("`--table` is required for the mnesia-snapshot input"))?;
+            (contents.parse::<MnesiaSnapshot>()?.assignment(name)?, table)
+        }
+        InputFormat::Kafka => {
+            let reassignment = contents.parse::<KafkaReassignment>()?;
+            let topic = match (table, &reassignment.topics()[..]) {
+                (Some(topic), _) => topic,
+                (None, [topic]) => topic.to_string(),
+                (None, topics) => bail!("Choose one of the topics {topics:?} with `--topic`"),
+            };
+            (reassignment.assignment(&topic)?, Some(topic))
+        }
+    };
+    Ok((assignment.normalize(policy)?, table))
+}
 
-                        if ns1.len() != nodes_num {
-                            let nodes_str = ns
-                                    .iter()
-                                    .map(|n| n.0.as_str())
-                                    .collect::<Vec<_>>()
-                                    .join(", ");
-                            bail!("{p} replicas on duplicate nodes: {nodes_str}");
-                        }
-                        Ok(())
-                    })?;
-
-                    let nodes_map = assignment.nodes_map();
-                    let nodes_num = nodes_map.len();
-                    let avg = (partitions * factor) as f64 / nodes_num as f64;
-                    let expect_lower = avg.floor() as usize;
-                    let expect_upper = avg.ceil() as usize;
-                    let expect = if expect_lower == expect_upper {
-                        format!("{}", expect_lower)
-                    } else {
-                        format!("[{}, {}]", expect_lower, expect_upper)
-                    };
-                    for (n, ps) in &nodes_map {
-                        let ps_num = ps.len();
-                        if ps_num < expect_lower || ps_num > expect_upper {
-                            bail!("{n} has {ps_num} partitions, \
-                            but the balance number of partitions is {expect}");
-                        }
-                    }
-                }
+fn read_document(input: FileOrStdin<String>, input_format: InputFormat, policy: &NodePolicy)
+-> Result<Document>
+{
+    let contents = input.contents()?;
+    let document = match input_format {
+        InputFormat::Json => contents.parse(),
+        InputFormat::MnesiaSnapshot => contents.parse::<MnesiaSnapshot>()?.document(),
+        InputFormat::Kafka => contents.parse::<KafkaReassignment>()?.document(),
+    }?;
+    document.normalize(policy)
+}
 
-                match output_format {
-                    OutputFormat::Json => {
-                        println!("{}", serde_json::to_string_pretty(&assignment)?);
-                    }
-                    OutputFormat::Text => {
-                        assignment.print();
-                    }
-                }
-            }
-        }
+fn print_totals(totals: &BTreeMap<Node, usize>) {
+    println!("==== Totals: ====");
+    println!("Node\tTotal");
+    println!("----\t-----");
+    for (n, total) in totals {
+        println!("{:>4}\t{:>5}", n.as_str(), total);
+    }
+    let upper = totals.values().max().copied().unwrap_or(0);
+    let lower = totals.values().min().copied().unwrap_or(0);
+    println!();
+    println!("upper: {upper}, lower: {lower}, Differ: {}", upper - lower);
+}
 
-        Ok(())
+fn print_erlang(document: &Document) {
+    for (name, assignment) in document.tables() {
+        println!("%% Table: {name}");
+        println!("{}", assignment.to_erlang());
     }
 }
 
-impl<S: AsRef<str>> From<S> for Node {
-    fn from(s: S) -> Self {
-        Self(s.as_ref().to_string())
+fn print_assignment(assignment: &Assignment) {
+    print_partitions(assignment.partitions(), None);
+}
+
+fn print_actions(actions: &[Action]) {
+    println!("==== Actions: ====");
+    let mut sorted = actions.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|a| a.partition());
+    for a in sorted {
+        println!("{a}");
     }
 }
 
-impl std::fmt::Display for Node {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "Node({})", self.0)
+fn print_plan(plan: &Plan) {
+    println!("==== Plan: ====");
+    for (i, phase) in plan.phases().iter().enumerate() {
+        println!("Phase {}, {}:", i + 1, phase.stage);
+        for step in &phase.steps {
+            println!("    {step}");
+        }
     }
 }
 
-impl std::fmt::Display for Partition {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "Partition({})", self.0)
+fn print_batches(batches: &[Vec<Move>]) {
+    println!("==== Batches: ====");
+    for (i, batch) in batches.iter().enumerate() {
+        println!("Batch {}:", i + 1);
+        for m in batch {
+            println!("    Move {} from {} to {}", m.partition, m.from, m.to);
+        }
     }
 }
 
-impl<N: Into<u32>> From<N> for Partition {
-    fn from(n: N) -> Self {
-        Self(n.into())
+fn print_bytes(bytes: &Bytes) {
+    println!("==== Bytes: ====");
+    for m in &bytes.cost.moves {
+        let Move { partition, from, to } = &m.r#move;
+        println!("Move {partition} from {from} to {to}: {} bytes", m.bytes);
+    }
+    println!("Moved: {} bytes in {} moves", bytes.cost.bytes, bytes.cost.moves.len());
+    println!();
+    println!("Node\tBytes");
+    println!("----\t-----");
+    for (n, b) in &bytes.nodes {
+        println!("{:>4}\t{:>5}", n.as_str(), b);
     }
+    let upper = bytes.nodes.values().max().copied().unwrap_or(0);
+    let lower = bytes.nodes.values().min().copied().unwrap_or(0);
+    println!();
+    println!("upper: {upper}, lower: {lower}, Differ: {}", upper - lower);
 }
 
-impl FromIterator<(Partition, Vec<Node>)> for Assignment {
-    fn from_iter<T: IntoIterator<Item = (Partition, Vec<Node>)>>(iter: T) -> Self {
-        Self(iter.into_iter().collect())
+fn balance_by(
+    assignment: Assignment,
+    moves: Vec<Move>,
+    sizes: Option<&Sizes>,
+    balance_by: BalanceBy,
+    topology: &Topology,
+) -> Result<(Assignment, Vec<Move>)> {
+    match (balance_by, sizes) {
+        (BalanceBy::Count, _) => Ok((assignment, moves)),
+        (BalanceBy::Bytes, Some(sizes)) => Ok(assignment.balance_bytes(moves, sizes, topology)),
+        (BalanceBy::Bytes, None) => bail!("`--sizes` is required to balance by bytes"),
     }
 }
 
-impl std::str::FromStr for Assignment {
-    type Err = Error;
+fn moves_of(actions: &[Action]) -> Vec<Move> {
+    actions
+        .iter()
+        .filter_map(|a| match a {
+            Action::MoveReplica { partition, from, to } => Some(Move {
+                partition: *partition,
+                from: from.clone(),
+                to: to.clone(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let assignment: Assignment = serde_json::from_str(s)?;
-        Ok(assignment)
+fn balance_leaders(assignment: Assignment, topology: &Topology, enabled: bool)
+-> (Assignment, Vec<Action>)
+{
+    if enabled {
+        assignment.balance_leaders(topology)
+    } else {
+        (assignment, vec![])
     }
 }
 
-impl Assignment {
-
-    pub fn init(
-        nodes: &[Node],
-        partitions: usize,
-        replication_factor: usize,
-        starts_with_zero: bool,
-    ) -> Self {
-        let n = nodes.iter().cycle()
-            .take(partitions * replication_factor)
-            .cloned()
-            .collect::<Vec<_>>();
-
-        let assignment = n.windows(replication_factor)
-            .take(partitions)
-            .enumerate()
-            .map(|(i, nodes)| {
-                let partition_id = if starts_with_zero {
-                    i as u32
-                } else {
-                    i as u32 + 1
-                };
-                (Partition(partition_id), Vec::from(nodes))
-            })
-            .collect::<Assignment>();
+fn nodes_str(nodes: &[Node]) -> String {
+    nodes.iter().map(|n| n.as_str()).collect::<Vec<_>>().join(",")
+}
 
-        assignment.balance_boundary(vec![], None).0
+fn print_report(report: &Report, to_stderr: bool) {
+    let mut lines = vec!["==== Report: ====".to_string()];
+    if report.is_empty() {
+        lines.push("No violations".to_string());
+    } else {
+        lines.push("Severity\tTable\tPartition\tNode\tMessage".to_string());
+        lines.push("--------\t-----\t---------\t----\t-------".to_string());
     }
-
-    pub fn add_nodes<I>(self, adds: I) -> Result<(Self, Vec<Move>)>
-    where
-        I: IntoIterator<Item = Node> + Clone,
-        <I as IntoIterator>::IntoIter: Clone,
-    {
-        Ok(self.balance_boundary(vec![], adds))
+    for v in report.violations() {
+        let severity = match v.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        lines.push(format!(
+            "{:<8}\t{:>5}\t{:>9}\t{:>4}\t{}",
+            severity,
+            v.table.as_deref().unwrap_or("-"),
+            v.partition.map(|p| p.id().to_string()).unwrap_or("-".to_string()),
+            v.node.as_ref().map(|n| n.as_str()).unwrap_or("-"),
+            v.message,
+        ));
     }
-
-    pub fn remove_node(&self, remove: &Node, replication_factor: usize)
-    -> Result<(Assignment, Vec<Move>)>
-    {
-        let mut moves = vec![];
-
-        if !self.0.iter().any(|(_p, ns)| ns.contains(remove)) {
-            bail!("{remove} is not contained in the assignment");
+    lines.push(String::new());
+    lines.push(format!(
+        "errors: {}, warnings: {}",
+        report.count(Severity::Error),
+        report.count(Severity::Warning),
+    ));
+
+    for line in lines {
+        if to_stderr {
+            eprintln!("{line}");
+        } else {
+            println!("{line}");
         }
+    }
+}
 
-        let current_nodes = self.0.values().flatten().collect::<BTreeSet<_>>();
-        if current_nodes.len() <= replication_factor {
-            bail!("NO less nodes then the replication factor");
-        }
-
-        // partitions_on_remove: Partition => [Node]
-        let partitions_on_remove = self.0
-            .iter()
-            .filter(|(_p, ns)| ns.contains(remove))
-            .map(|(p, ns)| {
-                let ns = ns
-                    .iter()
-                    .filter(|&n| n != remove)
-                    .cloned()
-                    .collect::<Vec<_>>();
-                (*p, ns)
-            })
-            .collect::<BTreeMap<_, _>>();
-
-        let mut remains: Assignment = self.0
-            .iter()
-            .map(|(p, ns)| {
-                let ns = ns.iter().filter(|n| n != &remove).cloned().collect::<Vec<_>>();
-                (*p, ns)
-            })
-            .collect();
-
-        // transform remains to Node => Set(partitions)
-        let remains_nodes = self.nodes_map();
-
-        // Group by alternatives for these partitions
-        let mut groups: HashMap<_, Vec<(_, _)>> = Default::default();
-
-        // px: node => partitions(num) on remove
-        let mut px: HashMap<_, Vec<_>> = Default::default();
+impl Command {
+    fn exec(self, policy: &NodePolicy) -> Result<()> {
+        match self {
+            Self::Init {
+                partitions,
+                replication_factor,
+                nodes,
+                output_format,
+                with_actions,
+                starts_with_zero,
+                table,
+                tables,
+                balance_leaders: leaders,
+                topology: TopologyArgs { topology, weights },
+                strategy,
+            } => {
+                let topology = read_topology(topology, weights, policy)?;
+                let nodes = policy.normalize_all(&nodes)?;
+
+                if !tables.is_empty() {
+                    let document = Document::init_with_topology(
+                        &tables,
+                        &nodes[..],
+                        partitions.get() as usize,
+                        replication_factor.get() as usize,
+                        starts_with_zero,
+                        &topology,
+                    )?;
+                    let output = DocumentOutput::new(
+                        document,
+                        Default::default(),
+                        &topology,
+                        leaders,
+                        None,
+                        StorageType::Disc,
+                    );
+                    output.print("Initialized", &[], output_format, with_actions)?;
+                    return Ok(());
+                }
 
-        for (p, ns) in &partitions_on_remove {
-            for n in ns {
-                let v = px.entry(n).or_default();
-                v.push(*p);
+                let assignment = strategy.strategy().init(
+                    &nodes[..],
+                    partitions.get() as usize,
+                    replication_factor.get() as usize,
+                    starts_with_zero,
+                    &topology,
+                )?;
+                // The initial assignment has no data to move, so there is no leader promotions
+                // either
+                let (assignment, _promotions) = balance_leaders(assignment, &topology, leaders);
+
+                Output::new(assignment, vec![], None)
+                    .table(table, StorageType::Disc)
+                    .print("Initialized", &[], output_format, with_actions)?;
             }
-        }
-
-        let px = px.iter().map(|(n, ps)| (n, ps.len())).collect::<HashMap<_, _>>();
-
-        for (p, ns) in &partitions_on_remove {
-            let alters = remains_nodes
-                .iter()
-                .filter(|(_n, ps)| !ps.contains(p))
-                .map(|(n, ps)| (n.clone(), ps.len()))
-                .collect::<Vec<_>>();
-            let v = groups.entry(alters).or_default();
-            v.push((*p, ns.clone()));
-        }
-
-        let mut groups = groups.into_iter().collect::<Vec<_>>();
-        groups.sort_by(|(ns1, _), (ns2, _)| {
-            let sum_ns1 = ns1.iter().map(|(n, _)| px.get(&n).unwrap_or(&0)).sum::<usize>();
-            let sum_ns2 = ns2.iter().map(|(n, _)| px.get(&n).unwrap_or(&0)).sum::<usize>();
-            sum_ns2.cmp(&sum_ns1)
-        });
-
-        debug!("groups len: {}", groups.len());
-        while let Some((mut group_key, mut pps)) = groups.pop() {
-            let mut f: Vec<(Partition, Vec<Node>)> = Default::default();
-            while let Some((_p, _ns)) = pps.first() {
-                debug!("======== group_key {:?} ========", group_key);
-
-                group_key.sort_by(|(_n1, len1), (_n2, len2)| {
-                    len1.cmp(len2)
-                });
-                let upper = group_key.last().unwrap().1;
-                let lower = group_key.first().unwrap().1;
-
-                if upper == lower {
-                    // cycle
-                    let rest = group_key
-                        .iter()
-                        .map(|(n, _len)| n)
-                        .cycle()
-                        .take(pps.len())
-                        .zip(pps)
-                        .map(|(n, (p, mut ns))| {
-                            ns.push(n.clone());
-                            moves.push(Move {
-                                partition: p,
-                                from: remove.clone(),
-                                to: n.clone(),
-                            });
-                            (p, ns)
-                        });
-
-                    f.extend(rest);
-
-                    // update rest groups
-                    remains = remains.0.into_iter()
-                        .filter(|(_p, ns)| !ns.contains(remove))
-                        .chain(f.into_iter()).collect();
-
-                    break;
-                } else {
-                    let (p, mut ns) = pps.remove(0);
-                    let (picked_node, picked_node_count) = group_key.first_mut().unwrap();
-                    let picked_node = picked_node.clone();
-                    *picked_node_count += 1;
-                    debug!(">>> pick: {:?}", picked_node);
-                    moves.push(Move {
-                        partition: p,
-                        from: remove.clone(),
-                        to: picked_node.clone(),
-                    });
-
-                    ns.push(picked_node);
-
-                    // update rest groups
-                    remains = remains.0.into_iter()
-                        .filter(|(_p, ns)| !ns.contains(remove))
-                        .chain(Some((p, ns)).into_iter()).collect();
+            Self::Add {
+                nodes,
+                balance_leaders: leaders,
+                topology: TopologyArgs { topology, weights },
+                input: InputArgs { input, input_format, table, storage_type },
+                reassign: ReassignArgs {
+                    all_tables,
+                    balance_totals,
+                    sizes,
+                    balance_by: balance_by_arg,
+                    strategy,
+                    max_inbound_per_node,
+                    max_outbound_per_node,
+                },
+                plan: PlanArgs { with_actions, max_concurrent_per_node, output_format },
+            } => {
+                let topology = read_topology(topology, weights, policy)?;
+                let nodes = policy.normalize_all(&nodes)?;
+                if nodes.is_empty() {
+                    bail!("Empty nodes to add");
                 }
-            }
-            cal_groups(&remains, &mut groups);
-        }
-
-        // If upper bound - lower bound > 1, then need to reassign, just move a partition from
-        // the node with the most partitions to the node with the least partitions.
-        Ok(remains.balance_boundary(moves, None))
-    }
 
-    pub fn validate(&self, replication_factor: usize) -> Result<()> {
-        if self.0.is_empty() {
-            bail!("Assignment must not be empty");
-        }
-
-        let nodes = self.0.values().flatten().collect::<BTreeSet<_>>();
-        if nodes.len() < replication_factor {
-            bail!("Nodes must be greater than or equal to replication factor");
-        }
+                if all_tables {
+                    let document = read_document(input, input_format, policy)?;
+                    let (document, moves) = document.add_nodes_with_topology(&nodes, &topology)?;
+                    let (document, moves) = if balance_totals {
+                        document.balance_totals(moves, &topology)
+                    } else {
+                        (document, moves)
+                    };
 
-        Ok(())
-    }
+                    let output = DocumentOutput::new(
+                        document,
+                        moves,
+                        &topology,
+                        leaders,
+                        max_concurrent_per_node,
+                        storage_type,
+                    );
+                    output
+                        .throttle(max_inbound_per_node, max_outbound_per_node)
+                        .print(
+                            &format!("After add nodes: {}", nodes_str(&nodes)),
+                            &[],
+                            output_format,
+                            with_actions,
+                        )?;
+                    return Ok(());
+                }
 
-    pub fn contains_node(&self, node: &Node) -> bool {
-        self.0.iter().any(|(_p, ns)| ns.contains(node))
-    }
+                let (assignment, table) = read_assignment(input, input_format, table, policy)?;
+
+                let sizes = sizes.map(|s| s.contents()).transpose()?;
+                let (assignment, moves) = strategy.strategy().add(assignment, &nodes, &topology)?;
+                let (assignment, moves) =
+                    balance_by(assignment, moves, sizes.as_ref(), balance_by_arg, &topology)?;
+                let (assignment, promotions) = balance_leaders(assignment, &topology, leaders);
+                let actions = moves.into_iter().map(Action::from).chain(promotions).collect();
+
+                Output::new(assignment, actions, max_concurrent_per_node)
+                    .table(table, storage_type)
+                    .throttle(max_inbound_per_node, max_outbound_per_node)
+                    .sizes(sizes.as_ref())
+                    .print(
+                        &format!("After add nodes: {}", nodes_str(&nodes)),
+                        &[],
+                        output_format,
+                        with_actions,
+                    )?;
+            }
+            Self::Remove {
+                nodes,
+                balance_leaders: leaders,
+                topology: TopologyArgs { topology, weights },
+                replication_factor,
+                input: InputArgs { input, input_format, table, storage_type },
+                reassign: ReassignArgs {
+                    all_tables,
+                    balance_totals,
+                    sizes,
+                    balance_by: balance_by_arg,
+                    strategy,
+                    max_inbound_per_node,
+                    max_outbound_per_node,
+                },
+                plan: PlanArgs { with_actions, max_concurrent_per_node, output_format },
+            } => {
+                let topology = read_topology(topology, weights, policy)?;
+                let nodes = policy.normalize_all(&nodes)?;
+
+                if all_tables {
+                    let document = read_document(input, input_format, policy)?;
+                    for node in &nodes {
+                        if !document.nodes().contains(node) {
+                            bail!("{node} does not exist in any table");
+                        }
+                    }
 
-    pub fn ensure_contains_node(&self, node: &Node) -> Result<()> {
-        if !self.contains_node(node) {
-            bail!("{node} is not contained in the assignment");
-        }
+                    let notes = document
+                        .tables()
+                        .iter()
+                        .flat_map(|(name, assignment)| {
+                            let nodes_map = assignment.nodes_map();
+                            nodes.iter().filter_map(move |n| {
+                                let s = nodes_map
+                                    .get(n)?
+                                    .iter()
+                                    .map(|p| format!("{}", p.id()))
+                                    .collect::<Vec<_>>().join(", ");
+                                Some(format!("Removed node: {n}, table: {name}, partitions: [{s}]"))
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    let (document, moves) = document.remove_nodes_with_topology(
+                        &nodes,
+                        replication_factor.map(|r| r.get() as usize),
+                        &topology,
+                    )?;
+                    let (document, moves) = if balance_totals {
+                        document.balance_totals(moves, &topology)
+                    } else {
+                        (document, moves)
+                    };
 
-        Ok(())
-    }
+                    let output = DocumentOutput::new(
+                        document,
+                        moves,
+                        &topology,
+                        leaders,
+                        max_concurrent_per_node,
+                        storage_type,
+                    );
+                    output
+                        .throttle(max_inbound_per_node, max_outbound_per_node)
+                        .print(
+                            &format!("After remove nodes: {}", nodes_str(&nodes)),
+                            &notes,
+                            output_format,
+                            with_actions,
+                        )?;
+                    return Ok(());
+                }
 
-    pub fn print(&self) {
-        print_partitions(&self.0, None);
-    }
+                let (assignment, table) = read_assignment(input, input_format, table, policy)?;
+                let replication_factor = replication_factor
+                    .ok_or_else(|| anyhow!("The replication factor is required"))?
+                    .get() as usize;
+                assignment.validate(replication_factor)?;
+                for node in &nodes {
+                    assignment.ensure_contains_node(node)?;
+                }
 
-    fn balance_boundary<I>(mut self, mut moves: Vec<Move>, adds: I) -> (Self, Vec<Move>)
-    where
-        I: IntoIterator<Item = Node> + Clone,
-        <I as IntoIterator>::IntoIter: Clone,
-    {
-        let mut nodes_map = self.nodes_map();
-        // let adds = adds.clone().into_iter();
-        for add in adds.clone() {
-            nodes_map.entry(add).or_default();
-        }
+                let mut nodes_map = assignment.nodes_map();
+                let notes = nodes
+                    .iter()
+                    .map(|n| {
+                        let s = nodes_map
+                            .remove(n)
+                            .unwrap_or_default()
+                            .iter()
+                            .map(|p| format!("{}", p.id()))
+                            .collect::<Vec<_>>().join(", ");
+                        format!("Removed node: {n}, partitions: [{s}]")
+                    })
+                    .collect::<Vec<_>>();
+                let sizes = sizes.map(|s| s.contents()).transpose()?;
+                let (assignment, moves) = strategy
+                    .strategy()
+                    .remove(assignment, &nodes, replication_factor, &topology)?;
+                let (assignment, moves) =
+                    balance_by(assignment, moves, sizes.as_ref(), balance_by_arg, &topology)?;
+                let (assignment, promotions) = balance_leaders(assignment, &topology, leaders);
+                let actions = moves.into_iter().map(Action::from).chain(promotions).collect();
+
+                Output::new(assignment, actions, max_concurrent_per_node)
+                    .table(table, storage_type)
+                    .throttle(max_inbound_per_node, max_outbound_per_node)
+                    .sizes(sizes.as_ref())
+                    .print(
+                        &format!("After remove nodes: {}", nodes_str(&nodes)),
+                        &notes,
+                        output_format,
+                        with_actions,
+                    )?;
+            }
+            Self::Rebalance {
+                balance_leaders: leaders,
+                topology: TopologyArgs { topology, weights },
+                input: InputArgs { input, input_format, table, storage_type },
+                reassign: ReassignArgs {
+                    all_tables,
+                    balance_totals,
+                    sizes,
+                    balance_by: balance_by_arg,
+                    strategy,
+                    max_inbound_per_node,
+                    max_outbound_per_node,
+                },
+                plan: PlanArgs { with_actions, max_concurrent_per_node, output_format },
+            } => {
+                let topology = read_topology(topology, weights, policy)?;
 
-        if nodes_map.len() <= 1 {
-            return (self, moves);
-        }
+                if all_tables {
+                    let document = read_document(input, input_format, policy)?;
+                    let (document, moves) = document.rebalance(&topology);
+                    let (document, moves) = if balance_totals {
+                        document.balance_totals(moves, &topology)
+                    } else {
+                        (document, moves)
+                    };
 
-        let mut nodes = nodes_map.iter().map(|(n, ps)| (n, ps)).collect::<Vec<_>>();
-        nodes.sort_by(|(_n1, ps1), (_n2, ps2)| {
-            ps1.len().cmp(&ps2.len())
-        });
+                    let output = DocumentOutput::new(
+                        document,
+                        moves,
+                        &topology,
+                        leaders,
+                        max_concurrent_per_node,
+                        storage_type,
+                    );
+                    output
+                        .throttle(max_inbound_per_node, max_outbound_per_node)
+                        .print("After rebalance", &[], output_format, with_actions)?;
+                    return Ok(());
+                }
 
-        if nodes.last().unwrap().1.len() - nodes.first().unwrap().1.len() <= 1 {
-            return (self, moves);
-        }
+                let (assignment, table) = read_assignment(input, input_format, table, policy)?;
+                let sizes = sizes.map(|s| s.contents()).transpose()?;
+                let (assignment, moves) = strategy.strategy().rebalance(assignment, &topology)?;
+                let (assignment, moves) =
+                    balance_by(assignment, moves, sizes.as_ref(), balance_by_arg, &topology)?;
+                let (assignment, promotions) = balance_leaders(assignment, &topology, leaders);
+                let actions = moves.into_iter().map(Action::from).chain(promotions).collect();
+
+                Output::new(assignment, actions, max_concurrent_per_node)
+                    .table(table, storage_type)
+                    .throttle(max_inbound_per_node, max_outbound_per_node)
+                    .sizes(sizes.as_ref())
+                    .print("After rebalance", &[], output_format, with_actions)?;
+            }
+            Self::Replace {
+                from,
+                to,
+                input: InputArgs { input, input_format, table, storage_type },
+                plan: PlanArgs { with_actions, max_concurrent_per_node, output_format },
+            } => {
+                let from = policy.normalize(&from)?;
+                let to = policy.normalize(&to)?;
+                let (assignment, table) = read_assignment(input, input_format, table, policy)?;
+                let (assignment, moves) = assignment.replace_node(&from, &to)?;
+                let actions = moves.into_iter().map(Action::from).collect();
+
+                Output::new(assignment, actions, max_concurrent_per_node)
+                    .table(table, storage_type)
+                    .print(
+                        &format!("After replace node: {} with {}", from.as_str(), to.as_str()),
+                        &[],
+                        output_format,
+                        with_actions,
+                    )?;
+            }
+            Self::SetReplicationFactor {
+                replication_factor,
+                balance_leaders: leaders,
+                topology: TopologyArgs { topology, weights },
+                input: InputArgs { input, input_format, table, storage_type },
+                plan: PlanArgs { with_actions, max_concurrent_per_node, output_format },
+            } => {
+                let topology = read_topology(topology, weights, policy)?;
+                let (assignment, table) = read_assignment(input, input_format, table, policy)?;
+                let (assignment, mut actions) =
+                    assignment.set_replication_factor(replication_factor.get() as usize, &topology)?;
+                let (assignment, promotions) = balance_leaders(assignment, &topology, leaders);
+                actions.extend(promotions);
+
+                Output::new(assignment, actions, max_concurrent_per_node)
+                    .table(table, storage_type)
+                    .print(
+                        &format!("After set replication factor: {replication_factor}"),
+                        &[],
+                        output_format,
+                        with_actions,
+                    )?;
+            }
+            Self::AddPartitions {
+                count,
+                balance_leaders: leaders,
+                topology: TopologyArgs { topology, weights },
+                input: InputArgs { input, input_format, table, storage_type },
+                plan: PlanArgs { with_actions, max_concurrent_per_node, output_format },
+            } => {
+                let topology = read_topology(topology, weights, policy)?;
+                let (assignment, table) = read_assignment(input, input_format, table, policy)?;
+                let (assignment, mut actions) =
+                    assignment.add_partitions(count.get() as usize, &topology)?;
+                let (assignment, promotions) = balance_leaders(assignment, &topology, leaders);
+                actions.extend(promotions);
+
+                Output::new(assignment, actions, max_concurrent_per_node)
+                    .table(table, storage_type)
+                    .print(
+                        &format!("After add partitions: {count}"),
+                        &[],
+                        output_format,
+                        with_actions,
+                    )?;
+            }
+            Self::RemovePartitions {
+                count,
+                balance_leaders: leaders,
+                topology: TopologyArgs { topology, weights },
+                input: InputArgs { input, input_format, table, storage_type },
+                plan: PlanArgs { with_actions, max_concurrent_per_node, output_format },
+            } => {
+                let topology = read_topology(topology, weights, policy)?;
+                let (assignment, table) = read_assignment(input, input_format, table, policy)?;
+                let (assignment, mut actions) =
+                    assignment.remove_partitions(count.get() as usize, &topology)?;
+                let (assignment, promotions) = balance_leaders(assignment, &topology, leaders);
+                actions.extend(promotions);
+
+                Output::new(assignment, actions, max_concurrent_per_node)
+                    .table(table, storage_type)
+                    .print(
+                        &format!("After remove partitions: {count}"),
+                        &[],
+                        output_format,
+                        with_actions,
+                    )?;
+            }
+            Self::Tables { input, input_format, output_format } => {
+                let contents = input.contents()?;
+                let tables = match input_format {
+                    InputFormat::Json => bail!("No tables in the assignment JSON"),
+                    InputFormat::MnesiaSnapshot => {
+                        let snapshot = contents.parse::<MnesiaSnapshot>()?;
+                        snapshot.tables().into_iter().map(String::from).collect::<Vec<_>>()
+                    }
+                    InputFormat::Kafka => {
+                        let reassignment = contents.parse::<KafkaReassignment>()?;
+                        reassignment.topics().into_iter().map(String::from).collect()
+                    }
+                };
+                match output_format {
+                    OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&tables)?),
+                    OutputFormat::Text => {
+                        for table in tables {
+                            println!("{table}");
+                        }
+                    }
+                    OutputFormat::Erlang => {
+                        let tables = tables.iter().map(|t| atom(t)).collect::<Vec<_>>();
+                        println!("[{}].", tables.join(", "));
+                    }
+                    OutputFormat::MnesiaScript | OutputFormat::Kafka => {
+                        bail!("No such output format for tables")
+                    }
+                }
+            }
+            Self::Validate {
+                input,
+                input_format,
+                table,
+                all_tables,
+                check_totals,
+                partitions,
+                starts_with_zero,
+                first_id,
+                check_leaders,
+                topology: TopologyArgs { topology, weights },
+                replication_factor,
+                output_format,
+            } => {
+                let topology = read_topology(topology, weights, policy)?;
+                let partitions = partitions.map(|p| p.get() as usize);
+                let factor = replication_factor.map(|r| r.get() as usize);
+                let first_id = |assignment: &Assignment| {
+                    first_id
+                        .or(starts_with_zero.then_some(0))
+                        .unwrap_or_else(|| assignment.first_id())
+                };
 
-        // find a partition on the upper bound node but the lower bound node doesn't have
-        let upper = nodes.last().unwrap();
-        let lower = nodes.first().unwrap();
-        // Must have a partition on the upper bound node but the lower bound node doesn't have
-        let p = upper.1.difference(lower.1).next().unwrap();
-
-        // move p from upper to lower
-        debug!("Move {p} from upper bound node {} to lower bound node {}", upper.0.0, lower.0.0);
-
-        self.0.entry(*p).and_modify(|ns| {
-            // Use Vec::retian_mut to update the p's node list in place,
-            // change the node upper to lower
-            ns.retain_mut(|n| {
-                if n == upper.0 {
-                    moves.push(Move {
-                        partition: *p,
-                        from: upper.0.clone(),
-                        to: lower.0.clone(),
-                    });
-                    // swich partition p from upper bound node to lower bound node
-                    *n = lower.0.clone();
-                    true
+                let mut report = Report::default();
+                let (assignment, document, table) = if all_tables {
+                    let document = read_document(input, input_format, policy)?;
+                    for (name, assignment) in document.tables() {
+                        report.extend_table(name, assignment.report(
+                            first_id(assignment),
+                            partitions.unwrap_or(assignment.len()),
+                            factor.unwrap_or(assignment.replication_factor()),
+                            &topology,
+                            check_leaders,
+                        ));
+                    }
+                    if check_totals {
+                        report.extend(document.report_totals(&topology));
+                    }
+                    (None, Some(document), table)
                 } else {
-                    true
-                }
-            });
-        });
+                    let partitions = partitions.ok_or_else(|| anyhow!("The number of partitions is required"))?;
+                    let factor = factor.ok_or_else(|| anyhow!("The replication factor is required"))?;
+                    let (assignment, table) = read_assignment(input, input_format, table, policy)?;
+                    report = assignment.report(
+                        first_id(&assignment),
+                        partitions,
+                        factor,
+                        &topology,
+                        check_leaders,
+                    );
+                    (Some(assignment), None, table)
+                };
 
-        self.balance_boundary(moves, adds)
-    }
+                match output_format {
+                    OutputFormat::Json => {
+                        let output = ReportOutput {
+                            valid: !report.has_errors(),
+                            errors: report.count(Severity::Error),
+                            warnings: report.count(Severity::Warning),
+                            violations: report.violations(),
+                        };
+                        println!("{}", serde_json::to_string_pretty(&output)?);
+                    }
+                    OutputFormat::Text => {
+                        if let Some(assignment) = &assignment {
+                            print_assignment(assignment);
+                        }
+                        if let Some(document) = &document {
+                            for (name, assignment) in document.tables() {
+                                println!("==== Table: {name} ====");
+                                print_assignment(assignment);
+                            }
+                            print_totals(&document.totals());
+                        }
+                        print_report(&report, false);
+                    }
+                    // The other formats print the assignment as is, if valid
+                    _ if report.has_errors() => print_report(&report, true),
+                    OutputFormat::Erlang => {
+                        if let Some(assignment) = &assignment {
+                            println!("{}", assignment.to_erlang());
+                        }
+                        if let Some(document) = &document {
+                            print_erlang(document);
+                        }
+                    }
+                    OutputFormat::MnesiaScript => bail!("No mnesia-script output for validate"),
+                    OutputFormat::Kafka => {
+                        if let Some(assignment) = &assignment {
+                            let topic = table.ok_or_else(|| anyhow!("`--topic` is required for the kafka output"))?;
+                            println!("{}", KafkaReassignment::to_json([(topic.as_str(), assignment)])?);
+                        }
+                        if let Some(document) = &document {
+                            println!("{}", KafkaReassignment::to_json(
+                                document.tables().iter().map(|(t, a)| (t.as_str(), a)),
+                            )?);
+                        }
+                    }
+                }
 
-    fn nodes_map(&self) -> HashMap<Node, BTreeSet<Partition>> {
-        let mut nodes_map: HashMap<Node, BTreeSet<Partition>> = Default::default();
-        for (p, ns) in &self.0 {
-            for n in ns {
-                let v = nodes_map.entry(n.clone()).or_default();
-                v.insert(*p);
+                if report.has_errors() {
+                    bail!("Invalid assignment, {} errors found", report.count(Severity::Error));
+                }
             }
         }
 
-        nodes_map
+        Ok(())
     }
 }
@@ -0,0 +1,273 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, VecDeque};
+use log::debug;
+use anyhow::{bail, Result};
+
+use crate::{Assignment, Move, Node, Topology};
+
+/// An edge of the flow network, along with its reverse edge at `rev` of the `to` vertex.
+struct Edge {
+    to: usize,
+    cap: i64,
+    cost: i64,
+    rev: usize,
+}
+
+/// A flow network solved by the successive shortest paths, which allows negative costs as long as
+/// there is no negative cycle at the beginning.
+#[derive(Default)]
+struct Network(Vec<Vec<Edge>>);
+
+impl Network {
+    fn vertex(&mut self) -> usize {
+        self.0.push(vec![]);
+        self.0.len() - 1
+    }
+
+    /// Adds an edge, returns its index in the edges of `from`.
+    fn edge(&mut self, from: usize, to: usize, cap: i64, cost: i64) -> usize {
+        let (i, j) = (self.0[from].len(), self.0[to].len() + usize::from(from == to));
+        self.0[from].push(Edge { to, cap, cost, rev: j });
+        self.0[to].push(Edge { to: from, cap: 0, cost: -cost, rev: i });
+        i
+    }
+
+    /// Sends as much flow as possible from `s` to `t` with the minimum cost, returns the flow.
+    fn min_cost_flow(&mut self, s: usize, t: usize) -> i64 {
+        // The potentials keep the reduced costs non-negative for Dijkstra, starting from the
+        // shortest distances by Bellman-Ford, as the costs can be negative
+        let mut potentials = vec![0; self.0.len()];
+        let mut queued = vec![false; self.0.len()];
+        let mut queue = VecDeque::from([s]);
+        let mut dist = vec![i64::MAX; self.0.len()];
+        dist[s] = 0;
+        while let Some(u) = queue.pop_front() {
+            queued[u] = false;
+            for e in self.0[u].iter().filter(|e| e.cap > 0) {
+                if dist[u] + e.cost < dist[e.to] {
+                    dist[e.to] = dist[u] + e.cost;
+                    if !queued[e.to] {
+                        queued[e.to] = true;
+                        queue.push_back(e.to);
+                    }
+                }
+            }
+        }
+        for (h, d) in potentials.iter_mut().zip(&dist) {
+            if *d < i64::MAX {
+                *h = *d;
+            }
+        }
+
+        let mut flow = 0;
+        loop {
+            let mut dist = vec![i64::MAX; self.0.len()];
+            let mut heap = BinaryHeap::from([Reverse((0, s))]);
+            dist[s] = 0;
+            while let Some(Reverse((d, u))) = heap.pop() {
+                if d > dist[u] {
+                    continue;
+                }
+                for e in self.0[u].iter().filter(|e| e.cap > 0) {
+                    let next = d + e.cost + potentials[u] - potentials[e.to];
+                    if next < dist[e.to] {
+                        dist[e.to] = next;
+                        heap.push(Reverse((next, e.to)));
+                    }
+                }
+            }
+            if dist[t] == i64::MAX {
+                break;
+            }
+            for (h, d) in potentials.iter_mut().zip(&dist) {
+                if *d < i64::MAX {
+                    *h += *d;
+                }
+            }
+            flow += self.blocking_flow(s, t, &potentials);
+        }
+
+        flow
+    }
+
+    /// Sends the flow from `s` to `t` along the shortest paths only, i.e. the edges of zero cost
+    /// reduced by the `potentials`, as Dinic does, returns the flow.
+    fn blocking_flow(&mut self, s: usize, t: usize, potentials: &[i64]) -> i64 {
+        let admissible = |u: usize, e: &Edge| e.cap > 0 && e.cost + potentials[u] == potentials[e.to];
+        let mut flow = 0;
+        loop {
+            let mut levels = vec![usize::MAX; self.0.len()];
+            let mut queue = VecDeque::from([s]);
+            levels[s] = 0;
+            while let Some(u) = queue.pop_front() {
+                for e in &self.0[u] {
+                    if admissible(u, e) && levels[e.to] == usize::MAX {
+                        levels[e.to] = levels[u] + 1;
+                        queue.push_back(e.to);
+                    }
+                }
+            }
+            if levels[t] == usize::MAX {
+                return flow;
+            }
+
+            // The next edge to try of each vertex, the edges tried are never tried again
+            let mut next = vec![0; self.0.len()];
+            loop {
+                let mut path: Vec<(usize, usize)> = vec![];
+                let mut u = s;
+                while u != t {
+                    let found = self.0[u][next[u]..]
+                        .iter()
+                        .position(|e| admissible(u, e) && levels[e.to] == levels[u] + 1);
+                    match found {
+                        Some(i) => {
+                            next[u] += i;
+                            path.push((u, next[u]));
+                            u = self.0[u][next[u]].to;
+                        }
+                        None => {
+                            // A dead end, back off to try the next edge of the previous vertex
+                            next[u] = self.0[u].len();
+                            let Some((prev, i)) = path.pop() else {
+                                break;
+                            };
+                            next[prev] = i + 1;
+                            u = prev;
+                        }
+                    }
+                }
+                if path.is_empty() {
+                    break;
+                }
+
+                let push = path.iter().map(|&(u, i)| self.0[u][i].cap).min().unwrap();
+                for &(u, i) in &path {
+                    self.0[u][i].cap -= push;
+                    let Edge { to, rev, .. } = self.0[u][i];
+                    self.0[to][rev].cap += push;
+                }
+                flow += push;
+            }
+        }
+    }
+}
+
+impl Assignment {
+    /// Same as [`Assignment::add_nodes_with_topology`], but with the minimum number of moves, see
+    /// [`Assignment::rebalance_optimal`].
+    pub fn add_nodes_optimal(self, adds: &[Node], topology: &Topology) -> Result<(Self, Vec<Move>)> {
+        self.check_adds(adds)?;
+        let nodes = self.nodes().into_iter().chain(adds).cloned().collect::<Vec<_>>();
+        self.rebalance_optimal(&nodes, topology)
+    }
+
+    /// Same as [`Assignment::remove_nodes_with_topology`], but with the minimum number of moves,
+    /// see [`Assignment::rebalance_optimal`].
+    pub fn remove_nodes_optimal(
+        self,
+        removes: &[Node],
+        replication_factor: usize,
+        topology: &Topology,
+    ) -> Result<(Self, Vec<Move>)>
+    {
+        let nodes = self.remaining_nodes(removes, replication_factor)?;
+        self.rebalance_optimal(&nodes, topology)
+    }
+
+    /// Reassigns the partitions to `nodes` with the minimum number of moves, such that the number
+    /// of partitions on every node is within its target, and the replicas of each partition span
+    /// as many zones as possible.
+    ///
+    /// It's solved as a min-cost max-flow problem, from each partition through the zones to the
+    /// nodes, where keeping a replica on its node costs nothing and moving it costs one, and the
+    /// bounds of each node are forced by a large cost, so they are only broken if the zones leave
+    /// no other way. The result is provably minimal when the number of zones is no less than the
+    /// replication factor. Otherwise the zones are not fully modeled, and are repaired afterwards
+    /// as the greedy strategy does.
+    ///
+    /// Returns the new assignment and the moves to get there.
+    pub fn rebalance_optimal(mut self, nodes: &[Node], topology: &Topology)
+    -> Result<(Self, Vec<Move>)>
+    {
+        let nodes = nodes.iter().cloned().collect::<BTreeSet<_>>().into_iter().collect::<Vec<_>>();
+        let replication_factor = self.replication_factor();
+        if nodes.len() < replication_factor {
+            bail!("Nodes must be greater than or equal to replication factor");
+        }
+
+        let replicas = self.replicas();
+        let partitions = self.0.len();
+        let targets = topology.targets(&nodes, replicas, partitions);
+        let zones = topology.zones(&nodes).into_iter().collect::<Vec<_>>();
+        // Forces the bounds of the nodes over any number of moves
+        let force = replicas as i64 + 1;
+
+        let mut network = Network::default();
+        let (s, t) = (network.vertex(), network.vertex());
+        let node_vertices = nodes
+            .iter()
+            .map(|n| {
+                let v = network.vertex();
+                let target = &targets[n];
+                network.edge(v, t, target.lower as i64, -force);
+                network.edge(v, t, (target.upper - target.lower) as i64, 0);
+                // Over the upper bound only if the zones leave no other way
+                network.edge(v, t, (partitions - target.upper) as i64, force);
+                v
+            })
+            .collect::<Vec<_>>();
+
+        // The edges of each partition to each node, to read the flow back
+        let mut choices: BTreeMap<_, Vec<(usize, usize, &Node)>> = Default::default();
+        for (p, ns) in &self.0 {
+            let pv = network.vertex();
+            network.edge(s, pv, ns.len() as i64, 0);
+            let zone_cap = ns.len() - topology.required_spread(ns, zones.len()) + 1;
+            for zone in &zones {
+                let zv = network.vertex();
+                network.edge(pv, zv, zone_cap as i64, 0);
+                for (n, &nv) in nodes.iter().zip(&node_vertices) {
+                    if topology.zone(n) != *zone {
+                        continue;
+                    }
+                    let cost = if ns.contains(n) { 0 } else { 1 };
+                    let i = network.edge(zv, nv, 1, cost);
+                    choices.entry(*p).or_default().push((zv, i, n));
+                }
+            }
+        }
+
+        let flow = network.min_cost_flow(s, t);
+        if flow < replicas as i64 {
+            bail!("No balanced assignment of {replicas} replicas on the nodes, only {flow}");
+        }
+
+        let mut moves = vec![];
+        for (p, ns) in self.0.iter_mut() {
+            let chosen = choices[p]
+                .iter()
+                .filter(|(zv, i, _n)| network.0[*zv][*i].cap == 0)
+                .map(|(_zv, _i, n)| (*n).clone())
+                .collect::<BTreeSet<_>>();
+            let mut added = chosen
+                .iter()
+                .filter(|n| !ns.contains(n))
+                .cloned()
+                .collect::<Vec<_>>()
+                .into_iter();
+            // Replace the dropped replicas in place, so the others keep their positions
+            for n in ns.iter_mut() {
+                if !chosen.contains(n) {
+                    let to = added.next().unwrap();
+                    debug!("Move {p} from {n} to {to}");
+                    moves.push(Move { partition: *p, from: n.clone(), to: to.clone() });
+                    *n = to;
+                }
+            }
+        }
+
+        let (assignment, moves) = self.spread_zones(moves, &nodes, topology);
+        Ok(assignment.balance_boundary(moves, nodes, topology))
+    }
+}
@@ -0,0 +1,273 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{Action, Move, Node, Partition};
+
+/// An ordered execution plan of the actions, which copies the new replicas first, then switches
+/// over to them, and drops the old replicas last, so no partition is ever under-replicated.
+///
+/// Each stage is split into sequential phases, where the steps of a phase can run concurrently.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Plan(Vec<Phase>);
+
+/// A phase of the plan, the steps of which can run concurrently.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Phase {
+    pub stage: Stage,
+    pub steps: Vec<Step>,
+}
+
+/// The stages of the plan, in the order they are executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Copy,
+    Switch,
+    Drop,
+}
+
+/// A single step of the plan.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Step {
+    /// Create a replica of a partition on `node`, copied from the replica on `source` if any,
+    /// otherwise from any existing replica, or empty for a new partition.
+    Copy { partition: Partition, node: Node, source: Option<Node> },
+
+    /// Switch the partition over from the replica on `from` to the replica on `to`, e.g. hand over
+    /// the leadership. No data is moved.
+    Switch { partition: Partition, from: Node, to: Node },
+
+    /// Drop the replica of a partition on `node`.
+    Drop { partition: Partition, node: Node },
+}
+
+impl Plan {
+    /// Plans `actions`, with at most `max_per_node` concurrent steps touching any one node in
+    /// each phase, or no limit if `None`.
+    ///
+    /// The moves of the same replica are chained first, e.g. moving from `a` to `b` and then from
+    /// `b` to `c` is planned as a single move from `a` to `c`.
+    pub fn new(actions: &[Action], max_per_node: Option<usize>) -> Self {
+        let mut steps: Vec<Step> = vec![];
+        for action in chain(actions) {
+            match action {
+                Action::AddReplica { partition, node } => {
+                    steps.push(Step::Copy { partition, node, source: None });
+                }
+                Action::DropReplica { partition, node } => {
+                    steps.push(Step::Drop { partition, node });
+                }
+                Action::MoveReplica { partition, from, to } => {
+                    steps.push(Step::Copy { partition, node: to.clone(), source: Some(from.clone()) });
+                    steps.push(Step::Switch { partition, from: from.clone(), to });
+                    steps.push(Step::Drop { partition, node: from });
+                }
+                Action::PromoteLeader { partition, from, to } => {
+                    steps.push(Step::Switch { partition, from, to });
+                }
+                Action::CreatePartition { partition, nodes } => {
+                    steps.extend(nodes.into_iter().map(|node| Step::Copy { partition, node, source: None }));
+                }
+                Action::DeletePartition { partition, nodes } => {
+                    steps.extend(nodes.into_iter().map(|node| Step::Drop { partition, node }));
+                }
+            }
+        }
+
+        let mut phases: Vec<Phase> = vec![];
+        for stage in [Stage::Copy, Stage::Switch, Stage::Drop] {
+            let first = phases.len();
+            // The number of steps touching each node in each phase of this stage
+            let mut loads: Vec<BTreeMap<Node, usize>> = vec![];
+            // The steps of a partition keep their order, so a step never goes before the phase of
+            // an earlier step of the same partition
+            let mut earliest: BTreeMap<Partition, usize> = Default::default();
+
+            for step in steps.iter().filter(|s| s.stage() == stage) {
+                let nodes = step.nodes();
+                let start = earliest.get(&step.partition()).copied().unwrap_or(0);
+                let fits = |load: &BTreeMap<Node, usize>| match max_per_node {
+                    Some(max) => nodes.iter().all(|n| load.get(*n).copied().unwrap_or(0) < max),
+                    None => true,
+                };
+                let i = match (start..loads.len()).find(|&i| fits(&loads[i])) {
+                    Some(i) => i,
+                    None => {
+                        loads.push(Default::default());
+                        phases.push(Phase { stage, steps: vec![] });
+                        loads.len() - 1
+                    }
+                };
+                for n in nodes {
+                    *loads[i].entry(n.clone()).or_default() += 1;
+                }
+                earliest.insert(step.partition(), i);
+                phases[first + i].steps.push(step.clone());
+            }
+        }
+
+        Self(phases)
+    }
+
+    /// The phases of the plan, in order.
+    pub fn phases(&self) -> &[Phase] {
+        &self.0
+    }
+
+    /// Whether there is nothing to do at all.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Step {
+    /// The stage of the plan the step belongs to.
+    pub fn stage(&self) -> Stage {
+        match self {
+            Self::Copy { .. } => Stage::Copy,
+            Self::Switch { .. } => Stage::Switch,
+            Self::Drop { .. } => Stage::Drop,
+        }
+    }
+
+    /// The partition the step applies to.
+    pub fn partition(&self) -> Partition {
+        match self {
+            Self::Copy { partition, .. }
+            | Self::Switch { partition, .. }
+            | Self::Drop { partition, .. } => *partition,
+        }
+    }
+
+    /// The nodes the step touches.
+    pub fn nodes(&self) -> Vec<&Node> {
+        match self {
+            Self::Copy { node, source, .. } => std::iter::once(node).chain(source).collect(),
+            Self::Switch { from, to, .. } => vec![from, to],
+            Self::Drop { node, .. } => vec![node],
+        }
+    }
+}
+
+/// Splits `moves` into sequential batches, where at most `max_inbound` moves of a batch go to any
+/// one node, and at most `max_outbound` moves of a batch come from any one node, or no limit if
+/// `None`.
+///
+/// The moves of the same partition keep their order, each in a later batch than the previous one.
+pub fn batches(moves: &[Move], max_inbound: Option<usize>, max_outbound: Option<usize>)
+-> Vec<Vec<Move>>
+{
+    let mut batches: Vec<Vec<Move>> = vec![];
+    let mut inbounds: Vec<BTreeMap<&Node, usize>> = vec![];
+    let mut outbounds: Vec<BTreeMap<&Node, usize>> = vec![];
+    let mut next: BTreeMap<Partition, usize> = Default::default();
+    let under = |count: Option<&usize>, max: Option<usize>| match max {
+        Some(max) => count.copied().unwrap_or(0) < max,
+        None => true,
+    };
+
+    for m in moves {
+        let start = next.get(&m.partition).copied().unwrap_or(0);
+        let found = (start..batches.len()).find(|&i| {
+            under(inbounds[i].get(&m.to), max_inbound) && under(outbounds[i].get(&m.from), max_outbound)
+        });
+        let i = match found {
+            Some(i) => i,
+            None => {
+                batches.push(vec![]);
+                inbounds.push(Default::default());
+                outbounds.push(Default::default());
+                batches.len() - 1
+            }
+        };
+        *inbounds[i].entry(&m.to).or_default() += 1;
+        *outbounds[i].entry(&m.from).or_default() += 1;
+        next.insert(m.partition, i + 1);
+        batches[i].push(m.clone());
+    }
+
+    batches
+}
+
+/// Chains the moves of the same replica, and folds the moves of the newly added or created
+/// replicas into where they are finally placed.
+///
+/// A move to a node whose replica was dropped earlier keeps that replica instead, and drops the
+/// moving one, otherwise the copy would go to the node still holding the partition, and both
+/// replicas would be dropped.
+fn chain(actions: &[Action]) -> Vec<Action> {
+    let mut chained: Vec<Action> = vec![];
+    for action in actions {
+        let Action::MoveReplica { partition: p, from: f, to: t } = action else {
+            chained.push(action.clone());
+            continue;
+        };
+
+        let dropped = chained.iter().position(|a| {
+            matches!(a, Action::DropReplica { partition, node } if partition == p && node == t)
+        });
+        if let Some(i) = dropped {
+            chained[i] = Action::DropReplica { partition: *p, node: f.clone() };
+            continue;
+        }
+
+        let i = chained.iter().position(|a| match a {
+            Action::MoveReplica { partition, to, .. } => partition == p && to == f,
+            Action::AddReplica { partition, node } => partition == p && node == f,
+            Action::CreatePartition { partition, nodes } => partition == p && nodes.contains(f),
+            _ => false,
+        });
+        let Some(i) = i else {
+            chained.push(action.clone());
+            continue;
+        };
+
+        match &mut chained[i] {
+            Action::MoveReplica { from, .. } if from == t => {
+                // Moved back, nothing to do at all
+                chained.remove(i);
+            }
+            Action::MoveReplica { to, .. } => *to = t.clone(),
+            Action::AddReplica { node, .. } => *node = t.clone(),
+            Action::CreatePartition { nodes, .. } => {
+                for n in nodes.iter_mut().filter(|n| *n == f) {
+                    *n = t.clone();
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    chained
+}
+
+impl std::fmt::Display for Stage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Copy => write!(f, "copy"),
+            Self::Switch => write!(f, "switch"),
+            Self::Drop => write!(f, "drop"),
+        }
+    }
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Copy { partition, node, source: Some(source) } => {
+                write!(f, "Copy {partition} from {source} to {node}")
+            }
+            Self::Copy { partition, node, source: None } => {
+                write!(f, "Copy {partition} to {node}")
+            }
+            Self::Switch { partition, from, to } => {
+                write!(f, "Switch {partition} from {from} to {to}")
+            }
+            Self::Drop { partition, node } => {
+                write!(f, "Drop {partition} on {node}")
+            }
+        }
+    }
+}
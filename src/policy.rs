@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+use regex::Regex;
+use anyhow::{bail, Context, Result};
+
+use crate::{Assignment, Document, Node};
+
+/// The normalization rules of the node names, applied to every node read from the CLI or from the
+/// JSON input, so the same host is never taken as two different nodes, e.g.
+///
+/// ```
+/// use assignment::{Node, NodePolicy};
+///
+/// let policy = NodePolicy::new().with_trim(true).with_fold_case(true);
+/// assert_eq!(policy.normalize(&Node::from(" Node_1 ")).unwrap(), Node::from("node_1"));
+/// ```
+///
+/// An empty name is always rejected, and nothing else is changed or rejected by default.
+#[derive(Debug, Clone, Default)]
+pub struct NodePolicy {
+    trim: bool,
+    fold_case: bool,
+    pattern: Option<Regex>,
+}
+
+impl NodePolicy {
+    /// Creates a policy which only rejects the empty names.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether to trim the whitespaces around the names.
+    pub fn with_trim(mut self, trim: bool) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    /// Whether to fold the names to lowercase.
+    pub fn with_fold_case(mut self, fold_case: bool) -> Self {
+        self.fold_case = fold_case;
+        self
+    }
+
+    /// The regex the whole name must match after trimmed and folded, e.g. `[a-z0-9_.@-]+`.
+    pub fn with_pattern(mut self, pattern: &str) -> Result<Self> {
+        let regex = Regex::new(&format!("^(?:{pattern})$"))
+            .with_context(|| format!("Invalid node name pattern `{pattern}`"))?;
+        self.pattern = Some(regex);
+        Ok(self)
+    }
+
+    /// Normalizes the name of `node`, or fails if it's empty or doesn't match the pattern.
+    pub fn normalize(&self, node: &Node) -> Result<Node> {
+        let raw = node.as_str();
+        let mut name = raw;
+        if self.trim {
+            name = name.trim();
+        }
+        let name = if self.fold_case { name.to_lowercase() } else { name.to_string() };
+
+        if name.is_empty() {
+            bail!("Invalid node name `{raw}`, which is empty");
+        }
+
+        if let Some(pattern) = &self.pattern {
+            if !pattern.is_match(&name) {
+                bail!("Invalid node name `{raw}`, which doesn't match `{}`", pattern.as_str());
+            }
+        }
+
+        Ok(Node::new(name))
+    }
+
+    /// Normalizes `nodes` in order, see [`NodePolicy::normalize_map`].
+    pub fn normalize_all(&self, nodes: &[Node]) -> Result<Vec<Node>> {
+        let map = self.normalize_map(nodes)?;
+        Ok(nodes.iter().map(|n| map[n].clone()).collect())
+    }
+
+    /// Normalizes each distinct node of `nodes`, or fails if any two different names are
+    /// normalized to the same one, e.g. `Node_1` and `node_1` with case folding.
+    ///
+    /// Returns the normalized node of each node.
+    pub fn normalize_map<'a, I>(&self, nodes: I) -> Result<BTreeMap<Node, Node>>
+    where
+        I: IntoIterator<Item = &'a Node>,
+    {
+        let mut map: BTreeMap<Node, Node> = Default::default();
+        // The original name of each normalized name
+        let mut origins: BTreeMap<Node, Node> = Default::default();
+        for node in nodes {
+            if map.contains_key(node) {
+                continue;
+            }
+
+            let normalized = self.normalize(node)?;
+            if let Some(origin) = origins.get(&normalized) {
+                bail!(
+                    "Node names `{}` and `{}` collide as `{}`",
+                    origin.as_str(),
+                    node.as_str(),
+                    normalized.as_str(),
+                );
+            }
+            origins.insert(normalized.clone(), node.clone());
+            map.insert(node.clone(), normalized);
+        }
+
+        Ok(map)
+    }
+}
+
+impl Assignment {
+    /// Normalizes the names of all the nodes by `policy`, see [`NodePolicy::normalize_map`].
+    pub fn normalize(self, policy: &NodePolicy) -> Result<Self> {
+        let map = policy.normalize_map(self.0.values().flatten())?;
+        Ok(self.rename(&map))
+    }
+
+    fn rename(self, map: &BTreeMap<Node, Node>) -> Self {
+        self.0
+            .into_iter()
+            .map(|(p, ns)| (p, ns.iter().map(|n| map[n].clone()).collect()))
+            .collect()
+    }
+}
+
+impl Document {
+    /// Normalizes the names of all the nodes in all the tables by `policy`, the same node of
+    /// different tables must have the same name as well.
+    pub fn normalize(self, policy: &NodePolicy) -> Result<Self> {
+        let tables = self.into_inner();
+        let map = policy.normalize_map(tables.values().flat_map(|a| a.0.values().flatten()))?;
+        Ok(tables.into_iter().map(|(name, a)| (name, a.rename(&map))).collect())
+    }
+}
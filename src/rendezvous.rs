@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use log::debug;
+use anyhow::{bail, Result};
+
+use crate::{ensure_unique, Assignment, Move, Node, Partition, Topology};
+
+/// The hash of `node` for `partition`, FNV-1a finalized by SplitMix64, which is stable across runs,
+/// platforms and versions, unlike the hasher of the standard library.
+fn hash(node: &Node, partition: Partition) -> u64 {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for b in node.as_str().bytes().chain(partition.id().to_le_bytes()) {
+        h ^= b as u64;
+        h = h.wrapping_mul(0x100000001b3);
+    }
+
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d049bb133111eb);
+    h ^ (h >> 31)
+}
+
+/// The weighted rendezvous score of `node` for `partition`, the higher the better, where a node
+/// of twice the weight wins twice as many partitions.
+fn score(node: &Node, partition: Partition, topology: &Topology) -> f64 {
+    // Uniform in (0, 1)
+    let u = ((hash(node, partition) >> 11) as f64 + 0.5) / (1u64 << 53) as f64;
+    topology.weight(node) as f64 / -u.ln()
+}
+
+impl Assignment {
+    /// Initializes the assignment of `partitions` partitions with `replication_factor` replicas
+    /// each on `nodes` by rendezvous (highest random weight) hashing, which only depends on the
+    /// nodes, so it can be recomputed at any time without the previous assignment.
+    ///
+    /// Each partition takes the nodes of the highest scores of its own, where a node is skipped
+    /// once it reaches its bound, to keep the `upper bound - lower bound <= 1` guarantee, and the
+    /// replicas of each partition span as many distinct zones of `topology` as possible.
+    ///
+    /// Partition ids start with 0 if `starts_with_zero` is set, otherwise with 1.
+    pub fn init_rendezvous(
+        nodes: &[Node],
+        partitions: usize,
+        replication_factor: usize,
+        starts_with_zero: bool,
+        topology: &Topology,
+    ) -> Result<Self> {
+        if partitions == 0 {
+            bail!("Partitions must not be zero");
+        }
+
+        if replication_factor == 0 {
+            bail!("Replication factor must not be zero");
+        }
+
+        if nodes.is_empty() {
+            bail!("Nodes must not be empty");
+        }
+
+        ensure_unique(nodes)?;
+
+        if nodes.len() < replication_factor {
+            bail!("Nodes must be greater than or equal to replication factor");
+        }
+
+        let start = if starts_with_zero { 0 } else { 1 };
+        let partitions = (start..start + partitions as u32)
+            .map(|id| (Partition(id), replication_factor));
+        Ok(Self::place_rendezvous(partitions, nodes, topology))
+    }
+
+    /// Recomputes the assignment on `nodes` by rendezvous hashing, the same as
+    /// [`Assignment::init_rendezvous`] with the partitions and their replication factors as is.
+    ///
+    /// The replicas of each partition are the same as the recomputation, but the kept replicas
+    /// stay in place, so the leaders only change with the moves.
+    ///
+    /// Returns the new assignment and the moves to get there.
+    pub fn rebalance_rendezvous(mut self, nodes: &[Node], topology: &Topology)
+    -> Result<(Self, Vec<Move>)>
+    {
+        if nodes.len() < self.replication_factor() {
+            bail!("Nodes must be greater than or equal to replication factor");
+        }
+
+        let partitions = self.0.iter().map(|(p, ns)| (*p, ns.len())).collect::<Vec<_>>();
+        let placed = Self::place_rendezvous(partitions, nodes, topology);
+
+        let mut moves = vec![];
+        for (p, ns) in self.0.iter_mut() {
+            let chosen = &placed.0[p];
+            let mut added = chosen.iter().filter(|n| !ns.contains(n)).cloned().collect::<Vec<_>>();
+            added.reverse();
+            for n in ns.iter_mut().filter(|n| !chosen.contains(n)) {
+                let to = added.pop().unwrap();
+                debug!("Move {p} from {n} to {to}");
+                moves.push(Move { partition: *p, from: n.clone(), to: to.clone() });
+                *n = to;
+            }
+        }
+
+        Ok((self, moves))
+    }
+
+    /// Places the `partitions` with the number of their replicas on `nodes`, see
+    /// [`Assignment::init_rendezvous`].
+    fn place_rendezvous<I>(partitions: I, nodes: &[Node], topology: &Topology) -> Self
+    where
+        I: IntoIterator<Item = (Partition, usize)>,
+    {
+        let partitions = partitions.into_iter().collect::<Vec<_>>();
+        let replicas = partitions.iter().map(|(_p, count)| count).sum::<usize>();
+        let targets = topology.targets(nodes, replicas, partitions.len());
+        let zones_count = topology.zones(nodes).len();
+        let mut loads = nodes.iter().map(|n| (n, 0)).collect::<BTreeMap<_, _>>();
+        // The number of replicas which can still go beyond the lower bounds of the nodes
+        let mut extra = replicas.saturating_sub(targets.values().map(|t| t.lower).sum());
+
+        let mut assignment: BTreeMap<Partition, Vec<Node>> = Default::default();
+        for (p, count) in partitions {
+            let mut ranked = nodes.iter().collect::<Vec<_>>();
+            ranked.sort_by(|n1, n2| {
+                score(n2, p, topology).total_cmp(&score(n1, p, topology)).then_with(|| n1.cmp(n2))
+            });
+
+            let required = zones_count.min(count);
+            let mut chosen: Vec<Node> = vec![];
+            while chosen.len() < count {
+                let zones = topology.zones(&chosen);
+                // A zone already taken only if there are still enough replicas for the others
+                let allowed = |n: &Node| {
+                    !chosen.contains(n)
+                        && (!zones.contains(topology.zone(n))
+                            || count - chosen.len() > required.saturating_sub(zones.len()))
+                };
+                let under = |n: &Node| {
+                    let target = &targets[n];
+                    loads[n] < target.lower || (loads[n] < target.upper && extra > 0)
+                };
+
+                // The bounds or even the zones are given up only if there is no other way, which
+                // is left to the rebalancing below
+                let n = ranked
+                    .iter()
+                    .find(|n| allowed(n) && under(n))
+                    .or_else(|| ranked.iter().find(|n| allowed(n)))
+                    .or_else(|| ranked.iter().find(|n| !chosen.contains(n)))
+                    .map(|&n| n.clone())
+                    .unwrap();
+
+                let load = loads.get_mut(&n).unwrap();
+                if *load >= targets[&n].lower {
+                    extra = extra.saturating_sub(1);
+                }
+                *load += 1;
+                chosen.push(n);
+            }
+            assignment.insert(p, chosen);
+        }
+
+        let assignment = Self(assignment);
+        let (assignment, moves) = assignment.spread_zones(vec![], nodes, topology);
+        let (assignment, moves) = assignment.balance_boundary(moves, nodes.to_vec(), topology);
+        if !moves.is_empty() {
+            debug!("Rebalanced {} replicas of the rendezvous placement", moves.len());
+        }
+
+        assignment
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::BTreeSet;
+use anyhow::{bail, Result};
+use log::debug;
+
+use crate::{Action, Assignment, Node, Partition, Topology};
+
+impl Assignment {
+    /// Changes the replication factor of every partition to `replication_factor`.
+    ///
+    /// Growing adds the new replicas to the least loaded nodes not holding the partition yet,
+    /// and shrinking drops the replicas from the most loaded nodes, both keeping the replicas
+    /// spread across as many distinct zones of `topology` as possible, then the nodes are balanced.
+    ///
+    /// Returns the new assignment, and the actions to get there, i.e. the added or dropped
+    /// replicas, and the moves to balance.
+    pub fn set_replication_factor(self, replication_factor: usize, topology: &Topology)
+    -> Result<(Self, Vec<Action>)>
+    {
+        if replication_factor == 0 {
+            bail!("Replication factor must not be zero");
+        }
+
+        if self.0.is_empty() {
+            bail!("Assignment must not be empty");
+        }
+
+        let nodes = self.nodes().into_iter().cloned().collect::<Vec<_>>();
+        if nodes.len() < replication_factor {
+            bail!("Nodes must be greater than or equal to replication factor");
+        }
+
+        let (assignment, mut actions) =
+            self.grow_replicas(replication_factor, &nodes, topology, |_p| true);
+        let (assignment, dropped) = assignment.shrink_replicas(replication_factor, &nodes, topology);
+        actions.extend(dropped);
+
+        let (assignment, moves) = assignment.spread_zones(vec![], &nodes, topology);
+        let (assignment, moves) = assignment.balance_boundary(moves, nodes, topology);
+        for m in moves {
+            let i = actions.iter().position(|a| match a {
+                Action::AddReplica { partition, node } => *partition == m.partition && *node == m.from,
+                Action::DropReplica { partition, node } => *partition == m.partition && *node == m.to,
+                _ => false,
+            });
+            match i.map(|i| &mut actions[i]) {
+                // A replica just added can be added to where it moves to directly
+                Some(Action::AddReplica { node, .. }) => *node = m.to,
+                // A replica moving to where another one was just dropped keeps that one in place,
+                // and drops the moving one instead
+                Some(Action::DropReplica { node, .. }) => *node = m.from,
+                _ => actions.push(m.into()),
+            }
+        }
+
+        Ok((assignment, actions))
+    }
+
+    /// Appends `count` partitions after the current maximum partition, with the same
+    /// replication factor as the existing partitions.
+    ///
+    /// The new replicas are placed on the least loaded nodes, spread across as many distinct zones
+    /// of `topology` as possible, then the nodes are balanced.
+    ///
+    /// Returns the new assignment, and the actions to get there, i.e. the created partitions and
+    /// the moves to balance.
+    pub fn add_partitions(mut self, count: usize, topology: &Topology)
+    -> Result<(Self, Vec<Action>)>
+    {
+        if count == 0 {
+            bail!("Partitions to add must not be zero");
+        }
+
+        let Some(last) = self.0.keys().next_back().copied() else {
+            bail!("Assignment must not be empty");
+        };
+        let replication_factor = self.0.values().map(|ns| ns.len()).max().unwrap_or(0);
+        let nodes = self.nodes().into_iter().cloned().collect::<Vec<_>>();
+
+        let Some(end) = u32::try_from(count).ok().and_then(|c| last.0.checked_add(c)) else {
+            bail!("Too many partitions to add after {last}");
+        };
+        let news = (last.0 + 1..=end).map(Partition).collect::<BTreeSet<_>>();
+        for p in &news {
+            self.0.insert(*p, vec![]);
+        }
+
+        let (assignment, _added) =
+            self.grow_replicas(replication_factor, &nodes, topology, |p| news.contains(p));
+        let (assignment, moves) = assignment.spread_zones(vec![], &nodes, topology);
+        let (assignment, moves) = assignment.balance_boundary(moves, nodes, topology);
+
+        // The new partitions are created where they end up, so only the moves of the existing
+        // partitions are left
+        let actions = news
+            .iter()
+            .map(|p| Action::CreatePartition { partition: *p, nodes: assignment.0[p].clone() })
+            .chain(moves.into_iter().filter(|m| !news.contains(&m.partition)).map(Action::from))
+            .collect();
+
+        Ok((assignment, actions))
+    }
+
+    /// Deletes the `count` trailing partitions, i.e. the ones with the highest ids, then
+    /// balances the remaining replicas with minimal moves.
+    ///
+    /// Returns the new assignment, and the actions to get there, i.e. the deleted partitions and
+    /// the moves to balance.
+    pub fn remove_partitions(mut self, count: usize, topology: &Topology)
+    -> Result<(Self, Vec<Action>)>
+    {
+        if count == 0 {
+            bail!("Partitions to remove must not be zero");
+        }
+
+        if count >= self.0.len() {
+            bail!("Can't remove {count} partitions from {} partitions", self.0.len());
+        }
+
+        let nodes = self.nodes().into_iter().cloned().collect::<Vec<_>>();
+        let mut actions = vec![];
+        for _ in 0..count {
+            let (p, ns) = self.0.pop_last().unwrap();
+            debug!("Delete {p}");
+            actions.push(Action::DeletePartition { partition: p, nodes: ns });
+        }
+
+        let (assignment, moves) = self.spread_zones(vec![], &nodes, topology);
+        let (assignment, moves) = assignment.balance_boundary(moves, nodes, topology);
+        actions.extend(moves.into_iter().map(Action::from));
+        Ok((assignment, actions))
+    }
+
+    /// Adds replicas to the partitions picked by `filter`, until `replication_factor`.
+    fn grow_replicas<F>(
+        mut self,
+        replication_factor: usize,
+        nodes: &[Node],
+        topology: &Topology,
+        filter: F,
+    ) -> (Self, Vec<Action>)
+    where
+        F: Fn(&Partition) -> bool,
+    {
+        let mut added = vec![];
+        let zones_count = topology.zones(nodes).len();
+        let targets = topology.targets(nodes, self.0.len() * replication_factor, self.0.len());
+        let mut nodes_map = self.nodes_map();
+        let deviation = |n: &Node, ps: Option<&BTreeSet<Partition>>| {
+            ps.map(|ps| ps.len()).unwrap_or(0) as f64 - targets[n].ideal
+        };
+
+        // One new replica for each partition in a round, so the load spreads evenly
+        while self.0.iter().any(|(p, ns)| filter(p) && ns.len() < replication_factor) {
+            let before = added.len();
+            for (p, ns) in self.0
+                .iter_mut()
+                .filter(|(p, ns)| filter(p) && ns.len() < replication_factor)
+            {
+                let zones = topology.zones(ns.iter());
+                let spread = ns.len() < zones_count;
+                let Some(node) = nodes
+                    .iter()
+                    .filter(|&n| !ns.contains(n))
+                    // Prefer the nodes in the zones not covered yet
+                    .min_by(|&n1, &n2| {
+                        let z1 = spread && zones.contains(topology.zone(n1));
+                        let z2 = spread && zones.contains(topology.zone(n2));
+                        z1.cmp(&z2).then_with(|| {
+                            deviation(n1, nodes_map.get(n1)).total_cmp(&deviation(n2, nodes_map.get(n2)))
+                        })
+                    })
+                    .cloned()
+                else {
+                    continue;
+                };
+
+                debug!("Add replica of {p} to {node}");
+                ns.push(node.clone());
+                nodes_map.entry(node.clone()).or_default().insert(*p);
+                added.push(Action::AddReplica { partition: *p, node });
+            }
+
+            if added.len() == before {
+                break;
+            }
+        }
+
+        (self, added)
+    }
+
+    /// Drops replicas from the partitions, until `replication_factor`.
+    fn shrink_replicas(mut self, replication_factor: usize, nodes: &[Node], topology: &Topology)
+    -> (Self, Vec<Action>)
+    {
+        let mut dropped = vec![];
+        let targets = topology.targets(nodes, self.0.len() * replication_factor, self.0.len());
+        let mut nodes_map = self.nodes_map();
+        let deviation = |n: &Node, ps: &BTreeSet<Partition>| ps.len() as f64 - targets[n].ideal;
+
+        // One replica dropped for each partition in a round, so the load spreads evenly
+        while self.0.values().any(|ns| ns.len() > replication_factor) {
+            for (p, ns) in self.0.iter_mut().filter(|(_p, ns)| ns.len() > replication_factor) {
+                // Prefer the replicas in the most crowded zones of the partition
+                let crowd = |n: &Node| {
+                    ns.iter().filter(|&m| topology.zone(m) == topology.zone(n)).count()
+                };
+                let i = ns
+                    .iter()
+                    .enumerate()
+                    .max_by(|(_i1, n1), (_i2, n2)| {
+                        crowd(n1).cmp(&crowd(n2)).then_with(|| {
+                            deviation(n1, &nodes_map[*n1]).total_cmp(&deviation(n2, &nodes_map[*n2]))
+                        })
+                    })
+                    .map(|(i, _n)| i)
+                    .unwrap();
+
+                let node = ns.remove(i);
+                debug!("Drop replica of {p} from {node}");
+                nodes_map.get_mut(&node).unwrap().remove(p);
+                dropped.push(Action::DropReplica { partition: *p, node });
+            }
+        }
+
+        (self, dropped)
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+
+use crate::{duplicates, Assignment, Document, Node, Partition, Target, Topology};
+
+/// How bad a violation is, only the errors make the assignment invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single problem found in the assignment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Violation {
+    pub severity: Severity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub table: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partition: Option<Partition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node: Option<Node>,
+    pub message: String,
+}
+
+/// All the violations found in the assignment, instead of only the first one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct Report(Vec<Violation>);
+
+impl Violation {
+    /// An error of `partition`.
+    pub fn partition(partition: Partition, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            table: None,
+            partition: Some(partition),
+            node: None,
+            message: message.into(),
+        }
+    }
+
+    /// An error of `node`.
+    pub fn node(node: Node, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            table: None,
+            partition: None,
+            node: Some(node),
+            message: message.into(),
+        }
+    }
+
+    /// Downgrades the violation to a warning.
+    pub fn warning(mut self) -> Self {
+        self.severity = Severity::Warning;
+        self
+    }
+}
+
+impl Report {
+    /// The violations, in the order they are found.
+    pub fn violations(&self) -> &[Violation] {
+        &self.0
+    }
+
+    /// Whether nothing is found at all.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The number of violations of `severity`.
+    pub fn count(&self, severity: Severity) -> usize {
+        self.0.iter().filter(|v| v.severity == severity).count()
+    }
+
+    /// Whether there is any error, i.e. the assignment is invalid.
+    pub fn has_errors(&self) -> bool {
+        self.count(Severity::Error) > 0
+    }
+
+    /// Adds a violation.
+    pub fn push(&mut self, violation: Violation) {
+        self.0.push(violation);
+    }
+
+    /// Appends the violations of `report`.
+    pub fn extend(&mut self, report: Report) {
+        self.0.extend(report.0);
+    }
+
+    /// Appends the violations of `report` found in `table`.
+    pub fn extend_table(&mut self, table: &str, report: Report) {
+        self.0.extend(
+            report.0.into_iter().map(|v| Violation { table: Some(table.to_string()), ..v }),
+        );
+    }
+}
+
+impl Assignment {
+    /// Checks the assignment has `partitions` partitions with consecutive ids from `first_id`,
+    /// without any gap or stray id out of the range, each with
+    /// `replication_factor` replicas on distinct nodes, the number of partitions on every node is
+    /// within its target, the replicas of each partition span as many distinct zones of `topology`
+    /// as possible, and the leaders are balanced, which is only a warning unless `check_leaders`
+    /// is set.
+    ///
+    /// Returns all the violations found.
+    pub fn report(
+        &self,
+        first_id: u32,
+        partitions: usize,
+        replication_factor: usize,
+        topology: &Topology,
+        check_leaders: bool,
+    ) -> Report {
+        let mut report = Report::default();
+
+        let ids = first_id..first_id.saturating_add(partitions as u32);
+        for p in self.0.keys().filter(|p| !ids.contains(&p.id())) {
+            report.push(Violation::partition(*p, format!(
+                "{p} is out of the expected range [{}, {}]",
+                ids.start,
+                ids.end.saturating_sub(1),
+            )));
+        }
+
+        for p in ids.map(Partition::new) {
+            let Some(ns) = self.0.get(&p) else {
+                report.push(Violation::partition(p, format!("{p} is missing")));
+                continue;
+            };
+
+            let nodes_num = ns.len();
+            if nodes_num != replication_factor {
+                report.push(Violation::partition(p, format!(
+                    "{p} replicas on {nodes_num} nodes, but replication factor is \
+                    {replication_factor}"
+                )));
+            }
+
+            let dups = duplicates(ns);
+            if !dups.is_empty() {
+                let nodes_str = dups.iter().map(|n| n.as_str()).collect::<Vec<_>>().join(", ");
+                report.push(Violation::partition(p, format!(
+                    "{p} replicas on duplicate nodes: {nodes_str}"
+                )));
+            }
+        }
+
+        let nodes_map = self.nodes_map().into_iter().collect::<BTreeMap<_, _>>();
+        let replicas = partitions * replication_factor;
+        let targets = topology.zone_targets(nodes_map.keys(), replicas, partitions);
+        for (n, ps) in &nodes_map {
+            let ps_num = ps.len();
+            let Target { lower, upper, .. } = targets[n];
+            if ps_num < lower || ps_num > upper {
+                report.push(Violation::node(n.clone(), format!(
+                    "{n} has {ps_num} partitions, but the balance number of partitions is {}",
+                    bounds(lower, upper),
+                )));
+            }
+        }
+
+        for p in self.zone_violations(topology) {
+            report.push(Violation::partition(p, format!(
+                "{p} replicas are not spread across as many zones as possible"
+            )));
+        }
+
+        // The leaders are only warned about unless asked to check
+        let targets = self.leader_targets(topology);
+        for (n, ps) in &self.leaders() {
+            let ps_num = ps.len();
+            let Target { lower, upper, .. } = targets[n];
+            if ps_num < lower || ps_num > upper {
+                let violation = Violation::node(n.clone(), format!(
+                    "{n} leads {ps_num} partitions, but the balance number of leaders is {}",
+                    bounds(lower, upper),
+                ));
+                report.push(if check_leaders { violation } else { violation.warning() });
+            }
+        }
+
+        report
+    }
+}
+
+impl Document {
+    /// Checks the total number of replicas on each node across all the tables is within its
+    /// target, see [`Document::total_targets`].
+    ///
+    /// Returns all the violations found.
+    pub fn report_totals(&self, topology: &Topology) -> Report {
+        let mut report = Report::default();
+        let targets = self.total_targets(topology);
+        for (n, total) in self.totals() {
+            let Target { lower, upper, .. } = targets[&n];
+            if total < lower || total > upper {
+                report.push(Violation::node(n.clone(), format!(
+                    "{n} has {total} replicas in total, but the balance number of replicas in \
+                    total is {}",
+                    bounds(lower, upper),
+                )));
+            }
+        }
+
+        report
+    }
+}
+
+/// The expected bounds, e.g. `12` or `[12, 13]`.
+fn bounds(lower: usize, upper: usize) -> String {
+    if lower == upper {
+        format!("{lower}")
+    } else {
+        format!("[{lower}, {upper}]")
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use log::debug;
+use anyhow::{Error, Result};
+
+use crate::{Assignment, Move, Node, Partition, Topology};
+
+/// The size of each partition in bytes, e.g. `{ "1": 10485760, "2": 42949672960 }`.
+///
+/// A partition without a size is taken as empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Sizes(BTreeMap<Partition, u64>);
+
+/// The cost of the moves, in the bytes to copy.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Cost {
+    /// Each move with the bytes it copies.
+    pub moves: Vec<MoveCost>,
+    /// The total bytes to copy.
+    pub bytes: u64,
+}
+
+/// A move with the bytes it copies.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MoveCost {
+    #[serde(flatten)]
+    pub r#move: Move,
+    pub bytes: u64,
+}
+
+impl std::str::FromStr for Sizes {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let sizes: Sizes = serde_json::from_str(s)?;
+        Ok(sizes)
+    }
+}
+
+impl FromIterator<(Partition, u64)> for Sizes {
+    fn from_iter<I: IntoIterator<Item = (Partition, u64)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl Sizes {
+    /// The size of `partition`, which is 0 if unknown.
+    pub fn size(&self, partition: &Partition) -> u64 {
+        self.0.get(partition).copied().unwrap_or(0)
+    }
+
+    /// The total bytes of the replicas on each node of `assignment`.
+    pub fn node_bytes(&self, assignment: &Assignment) -> BTreeMap<Node, u64> {
+        let mut bytes: BTreeMap<Node, u64> = Default::default();
+        for (p, ns) in &assignment.0 {
+            for n in ns {
+                *bytes.entry(n.clone()).or_default() += self.size(p);
+            }
+        }
+
+        bytes
+    }
+
+    /// The cost of `moves`, each copies the whole partition.
+    pub fn cost(&self, moves: &[Move]) -> Cost {
+        let moves = moves
+            .iter()
+            .map(|m| MoveCost { r#move: m.clone(), bytes: self.size(&m.partition) })
+            .collect::<Vec<_>>();
+        let bytes = moves.iter().map(|m| m.bytes).sum();
+
+        Cost { moves, bytes }
+    }
+}
+
+impl Assignment {
+    /// Moves partitions from the nodes holding the most bytes to the nodes holding the least,
+    /// proportional to their weights, as long as each move makes the bytes more even, without
+    /// breaking the zones spread of any partition.
+    ///
+    /// The number of partitions on the nodes is not balanced any more, but the bytes are.
+    pub fn balance_bytes(mut self, mut moves: Vec<Move>, sizes: &Sizes, topology: &Topology)
+    -> (Self, Vec<Move>)
+    {
+        let mut nodes_map = self.nodes_map();
+        if nodes_map.len() <= 1 {
+            return (self, moves);
+        }
+
+        let zones_count = topology.zones(nodes_map.keys()).len();
+        let weights = nodes_map.keys().map(|n| topology.weight(n) as f64).sum::<f64>();
+        let total = nodes_map.values().flatten().map(|p| sizes.size(p)).sum::<u64>() as f64;
+        let mut bytes = sizes.node_bytes(&self);
+        let deviation = |n: &Node, bytes: &BTreeMap<Node, u64>| {
+            bytes[n] as f64 - total * topology.weight(n) as f64 / weights
+        };
+
+        loop {
+            let mut nodes = nodes_map.keys().cloned().collect::<Vec<_>>();
+            nodes.sort_by(|n1, n2| deviation(n1, &bytes).total_cmp(&deviation(n2, &bytes)));
+
+            // The partition on the heavy node that best evens out the bytes with the light node,
+            // i.e. the size closest to the half of their difference
+            let found = nodes.iter().rev().find_map(|upper| {
+                nodes.iter().find_map(|lower| {
+                    let diff = deviation(upper, &bytes) - deviation(lower, &bytes);
+                    nodes_map[upper]
+                        .difference(&nodes_map[lower])
+                        .filter(|p| {
+                            let size = sizes.size(p) as f64;
+                            size > 0.0 && size < diff
+                        })
+                        .filter(|p| self.keeps_spread(p, upper, lower, topology, zones_count))
+                        .min_by(|p1, p2| {
+                            let d1 = (sizes.size(p1) as f64 - diff / 2.0).abs();
+                            let d2 = (sizes.size(p2) as f64 - diff / 2.0).abs();
+                            d1.total_cmp(&d2)
+                        })
+                        .map(|p| (*p, upper.clone(), lower.clone()))
+                })
+            });
+
+            let Some((p, upper, lower)) = found else {
+                break;
+            };
+
+            debug!("Move {p} of {} bytes from {upper} to {lower}", sizes.size(&p));
+            for n in self.0.get_mut(&p).unwrap().iter_mut().filter(|n| **n == upper) {
+                *n = lower.clone();
+            }
+            nodes_map.get_mut(&upper).unwrap().remove(&p);
+            nodes_map.get_mut(&lower).unwrap().insert(p);
+            *bytes.get_mut(&upper).unwrap() -= sizes.size(&p);
+            *bytes.get_mut(&lower).unwrap() += sizes.size(&p);
+            moves.push(Move { partition: p, from: upper, to: lower });
+        }
+
+        (self, moves)
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr, PickFirst};
+use anyhow::{anyhow, bail, Error, Result};
+
+use crate::{Assignment, Document, Node, Partition};
+
+/// The JSON dump of the Erlang/Mnesia cluster state, with the fragment distribution of each table
+/// under `.current_snapshot.table_distribution[].frag_dist`, e.g.
+/// `{ "current_snapshot": { "table_distribution": [ { "table_name": "mqtt_session", "frag_dist":
+/// [ { "frag_index": 1, "instances": [ "node_1", "node_2" ] }, ... ] } ] } }`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MnesiaSnapshot {
+    pub current_snapshot: CurrentSnapshot,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CurrentSnapshot {
+    #[serde(default)]
+    pub table_distribution: Vec<TableDistribution>,
+}
+
+/// The fragment distribution of a table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableDistribution {
+    pub table_name: String,
+    #[serde(default)]
+    pub frag_dist: Vec<FragDist>,
+}
+
+/// The nodes holding the replicas of a fragment, the `frag_index` is either a number or a string.
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FragDist {
+    #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    pub frag_index: u32,
+    pub instances: Vec<Node>,
+}
+
+impl std::str::FromStr for MnesiaSnapshot {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let snapshot: MnesiaSnapshot = serde_json::from_str(s)?;
+        Ok(snapshot)
+    }
+}
+
+impl MnesiaSnapshot {
+    /// The names of the tables in the snapshot.
+    pub fn tables(&self) -> Vec<&str> {
+        self.current_snapshot
+            .table_distribution
+            .iter()
+            .map(|t| t.table_name.as_str())
+            .collect()
+    }
+
+    /// The assignments of all the tables.
+    pub fn document(&self) -> Result<Document> {
+        self.tables()
+            .into_iter()
+            .map(|t| Ok((t.to_string(), self.assignment(t)?)))
+            .collect()
+    }
+
+    /// The assignment of `table`, with the fragments as the partitions.
+    pub fn assignment(&self, table: &str) -> Result<Assignment> {
+        let table = self.current_snapshot
+            .table_distribution
+            .iter()
+            .find(|t| t.table_name == table)
+            .ok_or_else(|| anyhow!("Table `{table}` not found in the snapshot"))?;
+
+        let mut partitions: BTreeMap<Partition, Vec<Node>> = Default::default();
+        for f in &table.frag_dist {
+            if partitions.insert(Partition::new(f.frag_index), f.instances.clone()).is_some() {
+                bail!("Duplicated fragment {} of table `{}`", f.frag_index, table.table_name);
+            }
+        }
+
+        Ok(partitions.into())
+    }
+}
@@ -0,0 +1,189 @@
+use anyhow::Result;
+
+use crate::{Assignment, Move, Node, Topology};
+
+/// A placement strategy, which places the partitions on the nodes initially, and reassigns them
+/// when nodes are added or removed, e.g.
+///
+/// ```
+/// use assignment::{GreedyStrategy, Node, Strategy, Topology};
+///
+/// let nodes: Vec<Node> = ["node_1", "node_2", "node_3"].into_iter().map(Node::from).collect();
+/// let topology = Topology::default();
+/// let assignment = GreedyStrategy.init(&nodes, 12, 2, false, &topology).unwrap();
+/// let adds = [Node::from("node_4")];
+/// let (assignment, _moves) = GreedyStrategy.add(assignment, &adds, &topology).unwrap();
+/// assert_eq!(assignment.nodes().len(), 4);
+/// ```
+pub trait Strategy {
+    /// Places `partitions` partitions with `replication_factor` replicas each on `nodes`.
+    ///
+    /// Partition ids start with 0 if `starts_with_zero` is set, otherwise with 1.
+    fn init(
+        &self,
+        nodes: &[Node],
+        partitions: usize,
+        replication_factor: usize,
+        starts_with_zero: bool,
+        topology: &Topology,
+    ) -> Result<Assignment>;
+
+    /// Adds `adds` nodes to `assignment` and moves partitions onto them.
+    ///
+    /// Returns the new assignment and the moves to get there.
+    fn add(&self, assignment: Assignment, adds: &[Node], topology: &Topology)
+    -> Result<(Assignment, Vec<Move>)>;
+
+    /// Removes `removes` nodes from `assignment` and moves their partitions to the remaining
+    /// nodes.
+    ///
+    /// Returns the new assignment and the moves to get there.
+    fn remove(
+        &self,
+        assignment: Assignment,
+        removes: &[Node],
+        replication_factor: usize,
+        topology: &Topology,
+    ) -> Result<(Assignment, Vec<Move>)>;
+
+    /// Rebalances `assignment` as is, which is [`Assignment::rebalance`] by default.
+    ///
+    /// Returns the new assignment and the moves to get there.
+    fn rebalance(&self, assignment: Assignment, topology: &Topology)
+    -> Result<(Assignment, Vec<Move>)>
+    {
+        Ok(assignment.rebalance(topology))
+    }
+}
+
+/// The round-robin placement, then spreading the zones and moving partitions between the upper
+/// and lower bound nodes step by step on changes.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GreedyStrategy;
+
+/// The round-robin placement, then the minimum number of moves on changes, see
+/// [`Assignment::rebalance_optimal`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OptimalStrategy;
+
+/// The rendezvous (highest random weight) hashing placement, which only depends on the nodes, see
+/// [`Assignment::init_rendezvous`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RendezvousStrategy;
+
+impl Strategy for GreedyStrategy {
+    fn init(
+        &self,
+        nodes: &[Node],
+        partitions: usize,
+        replication_factor: usize,
+        starts_with_zero: bool,
+        topology: &Topology,
+    ) -> Result<Assignment>
+    {
+        Assignment::init_with_topology(
+            nodes,
+            partitions,
+            replication_factor,
+            starts_with_zero,
+            topology,
+        )
+    }
+
+    fn add(&self, assignment: Assignment, adds: &[Node], topology: &Topology)
+    -> Result<(Assignment, Vec<Move>)>
+    {
+        assignment.add_nodes_with_topology(adds.to_vec(), topology)
+    }
+
+    fn remove(
+        &self,
+        assignment: Assignment,
+        removes: &[Node],
+        replication_factor: usize,
+        topology: &Topology,
+    ) -> Result<(Assignment, Vec<Move>)>
+    {
+        assignment.remove_nodes_with_topology(removes, replication_factor, topology)
+    }
+}
+
+impl Strategy for OptimalStrategy {
+    fn init(
+        &self,
+        nodes: &[Node],
+        partitions: usize,
+        replication_factor: usize,
+        starts_with_zero: bool,
+        topology: &Topology,
+    ) -> Result<Assignment>
+    {
+        // Nothing to move yet, the round-robin placement is balanced already
+        GreedyStrategy.init(nodes, partitions, replication_factor, starts_with_zero, topology)
+    }
+
+    fn add(&self, assignment: Assignment, adds: &[Node], topology: &Topology)
+    -> Result<(Assignment, Vec<Move>)>
+    {
+        assignment.add_nodes_optimal(adds, topology)
+    }
+
+    fn remove(
+        &self,
+        assignment: Assignment,
+        removes: &[Node],
+        replication_factor: usize,
+        topology: &Topology,
+    ) -> Result<(Assignment, Vec<Move>)>
+    {
+        assignment.remove_nodes_optimal(removes, replication_factor, topology)
+    }
+
+    fn rebalance(&self, assignment: Assignment, topology: &Topology)
+    -> Result<(Assignment, Vec<Move>)>
+    {
+        let nodes = assignment.nodes().into_iter().cloned().collect::<Vec<_>>();
+        assignment.rebalance_optimal(&nodes, topology)
+    }
+}
+
+impl Strategy for RendezvousStrategy {
+    fn init(
+        &self,
+        nodes: &[Node],
+        partitions: usize,
+        replication_factor: usize,
+        starts_with_zero: bool,
+        topology: &Topology,
+    ) -> Result<Assignment>
+    {
+        Assignment::init_rendezvous(nodes, partitions, replication_factor, starts_with_zero, topology)
+    }
+
+    fn add(&self, assignment: Assignment, adds: &[Node], topology: &Topology)
+    -> Result<(Assignment, Vec<Move>)>
+    {
+        assignment.check_adds(adds)?;
+        let nodes = assignment.nodes().into_iter().chain(adds).cloned().collect::<Vec<_>>();
+        assignment.rebalance_rendezvous(&nodes, topology)
+    }
+
+    fn remove(
+        &self,
+        assignment: Assignment,
+        removes: &[Node],
+        replication_factor: usize,
+        topology: &Topology,
+    ) -> Result<(Assignment, Vec<Move>)>
+    {
+        let nodes = assignment.remaining_nodes(removes, replication_factor)?;
+        assignment.rebalance_rendezvous(&nodes, topology)
+    }
+
+    fn rebalance(&self, assignment: Assignment, topology: &Topology)
+    -> Result<(Assignment, Vec<Move>)>
+    {
+        let nodes = assignment.nodes().into_iter().cloned().collect::<Vec<_>>();
+        assignment.rebalance_rendezvous(&nodes, topology)
+    }
+}
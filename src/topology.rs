@@ -0,0 +1,263 @@
+use std::collections::{BTreeMap, BTreeSet};
+use serde::{Deserialize, Serialize};
+use anyhow::{bail, Error, Result};
+
+use crate::{Node, NodePolicy};
+
+/// The topology of the nodes, i.e. the rack or availability zone each node is located in, and the
+/// weight of each node.
+///
+/// It is serialized as a JSON object, e.g. `{ "node_1": { "zone": "az1", "weight": 4 }, ... }`.
+///
+/// A node without a zone is treated as if it was the only node in its own zone, and a node without
+/// a weight has the weight of 1, so the default (empty) topology puts no constraint on the
+/// placement at all.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Topology(BTreeMap<Node, NodeInfo>);
+
+/// The topology information of a single node.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeInfo {
+    /// The rack or availability zone of the node.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone: Option<String>,
+
+    /// The weight of the node, the number of partitions on it is proportional to the weight.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
+}
+
+/// The balance target of the number of partitions on a node.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Target {
+    /// The ideal number of partitions, which may be fractional.
+    pub ideal: f64,
+    /// The lower bound, `floor(ideal)`.
+    pub lower: usize,
+    /// The upper bound, `ceil(ideal)`.
+    pub upper: usize,
+}
+
+impl std::str::FromStr for Topology {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let topology: Topology = serde_json::from_str(s)?;
+        for (n, info) in &topology.0 {
+            if info.weight == Some(0) {
+                bail!("The weight of {n} must not be zero");
+            }
+        }
+        Ok(topology)
+    }
+}
+
+impl Topology {
+    /// Creates an empty topology.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Puts `node` in `zone`.
+    pub fn set_zone(&mut self, node: Node, zone: impl Into<String>) {
+        self.0.entry(node).or_default().zone = Some(zone.into());
+    }
+
+    /// Same as [`Topology::set_zone`], but in a builder way.
+    pub fn with_zone(mut self, node: Node, zone: impl Into<String>) -> Self {
+        self.set_zone(node, zone);
+        self
+    }
+
+    /// Sets the weight of `node`.
+    pub fn set_weight(&mut self, node: Node, weight: u32) {
+        self.0.entry(node).or_default().weight = Some(weight);
+    }
+
+    /// Same as [`Topology::set_weight`], but in a builder way.
+    pub fn with_weight(mut self, node: Node, weight: u32) -> Self {
+        self.set_weight(node, weight);
+        self
+    }
+
+    /// Normalizes the names of the nodes by `policy`, see [`NodePolicy::normalize_map`].
+    pub fn normalize(self, policy: &NodePolicy) -> Result<Self> {
+        let map = policy.normalize_map(self.0.keys())?;
+        Ok(Self(self.0.into_iter().map(|(n, info)| (map[&n].clone(), info)).collect()))
+    }
+
+    /// The weight of `node`, which is 1 if no weight provided.
+    pub fn weight(&self, node: &Node) -> u32 {
+        self.0.get(node).and_then(|info| info.weight).unwrap_or(1).max(1)
+    }
+
+    /// The balance targets of `nodes` holding `replicas` replicas of `partitions` partitions in
+    /// total, proportional to their weights.
+    ///
+    /// A node holds at most one replica of each partition, so the targets of the heavy nodes are
+    /// capped at `partitions`, and the rest are spread over the other nodes.
+    pub fn targets<'a, I>(&self, nodes: I, replicas: usize, partitions: usize)
+    -> BTreeMap<Node, Target>
+    where
+        I: IntoIterator<Item = &'a Node>,
+    {
+        let mut targets: BTreeMap<Node, Target> = Default::default();
+        let mut rest = nodes.into_iter().collect::<BTreeSet<_>>();
+        let mut remains = replicas;
+
+        while !rest.is_empty() {
+            let weights = rest.iter().map(|&n| self.weight(n) as usize).sum::<usize>();
+            let capped = rest
+                .iter()
+                .copied()
+                .filter(|&n| remains * self.weight(n) as usize > partitions * weights)
+                .collect::<Vec<_>>();
+
+            if capped.is_empty() {
+                for n in rest {
+                    let share = remains * self.weight(n) as usize;
+                    targets.insert(n.clone(), Target {
+                        ideal: share as f64 / weights as f64,
+                        lower: share / weights,
+                        upper: share.div_ceil(weights),
+                    });
+                }
+                break;
+            }
+
+            for n in capped {
+                rest.remove(n);
+                remains = remains.saturating_sub(partitions);
+                targets.insert(n.clone(), Target {
+                    ideal: partitions as f64,
+                    lower: partitions,
+                    upper: partitions,
+                });
+            }
+        }
+
+        targets
+    }
+
+    /// Same as [`Topology::targets`], but within what the zones of `nodes` can take, when the
+    /// `replicas` replicas are spread evenly over the `partitions` partitions, and the replicas of
+    /// each partition span as many zones as possible.
+    ///
+    /// E.g. with 3 zones and 3 replicas, every zone takes exactly one replica of each partition,
+    /// however many nodes it has, so the nodes in the smaller zones take more partitions.
+    pub fn zone_targets<'a, I>(&self, nodes: I, replicas: usize, partitions: usize)
+    -> BTreeMap<Node, Target>
+    where
+        I: IntoIterator<Item = &'a Node>,
+    {
+        let nodes = nodes.into_iter().collect::<BTreeSet<_>>();
+        let mut zones: BTreeMap<&str, Vec<&Node>> = Default::default();
+        for &n in &nodes {
+            zones.entry(self.zone(n)).or_default().push(n);
+        }
+
+        let replication_factor = replicas.div_ceil(partitions.max(1));
+        let spread = zones.len().min(replication_factor);
+        // The bounds of the replicas of each zone, where a zone takes at most the replicas left
+        // after the other zones of the spread take one each, and every zone takes one if all the
+        // zones are needed for the spread
+        let bounds = |ns: &[&Node]| {
+            let most = ns.len().min(replication_factor + 1 - spread);
+            let least = if zones.len() <= replication_factor { 1 } else { 0 };
+            (partitions * least, partitions * most)
+        };
+
+        let mut rest = zones.keys().copied().collect::<BTreeSet<_>>();
+        let mut remains = replicas;
+        // The zones the bounds of which are reached, and the replicas they take
+        let mut fixed: BTreeMap<&str, usize> = Default::default();
+        while !rest.is_empty() {
+            let weight = |z: &str| zones[z].iter().map(|&n| self.weight(n) as usize).sum::<usize>();
+            let weights = rest.iter().map(|&z| weight(z)).sum::<usize>();
+            let share = |z: &str| remains as f64 * weight(z) as f64 / weights as f64;
+            let over = rest
+                .iter()
+                .copied()
+                .filter(|&z| share(z) > bounds(&zones[z]).1 as f64)
+                .collect::<Vec<_>>();
+            let reached = if over.is_empty() {
+                rest.iter()
+                    .copied()
+                    .filter(|&z| share(z) < bounds(&zones[z]).0 as f64)
+                    .map(|z| (z, bounds(&zones[z]).0))
+                    .collect::<Vec<_>>()
+            } else {
+                over.into_iter().map(|z| (z, bounds(&zones[z]).1)).collect()
+            };
+            if reached.is_empty() {
+                break;
+            }
+
+            for (z, count) in reached {
+                rest.remove(z);
+                remains = remains.saturating_sub(count);
+                fixed.insert(z, count);
+            }
+        }
+
+        let rest = rest.iter().flat_map(|z| zones[z].iter().copied());
+        let mut targets = self.targets(rest, remains, partitions);
+        for (z, count) in fixed {
+            targets.extend(self.targets(zones[z].iter().copied(), count, partitions));
+        }
+
+        targets
+    }
+
+    /// The zone of `node`, which is the node name itself if no zone provided.
+    pub fn zone<'a>(&'a self, node: &'a Node) -> &'a str {
+        self.0
+            .get(node)
+            .and_then(|info| info.zone.as_deref())
+            .unwrap_or(node.as_str())
+    }
+
+    /// The distinct zones of `nodes`.
+    pub fn zones<'a, I>(&'a self, nodes: I) -> BTreeSet<&'a str>
+    where
+        I: IntoIterator<Item = &'a Node>,
+    {
+        nodes.into_iter().map(|n| self.zone(n)).collect()
+    }
+
+    /// The number of distinct zones the replicas `ns` of a partition must span, when the
+    /// nodes in the cluster span `zones_count` zones.
+    pub(crate) fn required_spread(&self, ns: &[Node], zones_count: usize) -> usize {
+        zones_count.min(ns.len())
+    }
+
+    /// Whether the replicas `ns` of a partition span as many distinct zones as possible.
+    pub fn is_spread(&self, ns: &[Node], zones_count: usize) -> bool {
+        self.zones(ns).len() >= self.required_spread(ns, zones_count)
+    }
+
+    /// Orders `nodes` so that the adjacent nodes are in different zones as far as possible,
+    /// which is what the round-robin placement relies on.
+    pub(crate) fn interleave(&self, nodes: &[Node]) -> Vec<Node> {
+        // Keep the order of the zones as they first appear, so without zones the nodes stay as is
+        let mut zones: Vec<(&str, Vec<&Node>)> = vec![];
+        for n in nodes {
+            let zone = self.zone(n);
+            match zones.iter_mut().find(|(z, _)| *z == zone) {
+                Some((_, ns)) => ns.push(n),
+                None => zones.push((zone, vec![n])),
+            }
+        }
+
+        let mut zones = zones.into_iter().map(|(_z, ns)| ns).collect::<Vec<_>>();
+        // Zones with more nodes go first, so they get the most spread out
+        zones.sort_by_key(|z| std::cmp::Reverse(z.len()));
+
+        let rounds = zones.first().map(|z| z.len()).unwrap_or(0);
+        (0..rounds)
+            .flat_map(|i| zones.iter().filter_map(move |z| z.get(i)))
+            .map(|&n| n.clone())
+            .collect()
+    }
+}
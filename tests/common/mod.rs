@@ -0,0 +1,74 @@
+// Each test crate only uses some of the helpers
+#![allow(dead_code)]
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use assignment::{Assignment, Node, Partition, Plan, Step};
+
+pub fn nodes(names: &[&str]) -> Vec<Node> {
+    names.iter().copied().map(Node::from).collect()
+}
+
+/// Runs the CLI with `args`, writing `stdin` to it.
+pub fn run(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_assignment"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Asserts `result` is an error containing `message`.
+pub fn assert_rejected<T>(result: anyhow::Result<T>, message: &str) {
+    let Err(e) = result else {
+        panic!("accepted, but expected: {message}");
+    };
+    assert!(format!("{e:#}").contains(message), "unexpected error: {e:#}");
+}
+
+/// The nodes of the replicas of each partition, regardless of the order.
+pub fn replica_sets(assignment: &Assignment) -> BTreeMap<Partition, BTreeSet<Node>> {
+    assignment
+        .partitions()
+        .iter()
+        .map(|(p, ns)| (*p, ns.iter().cloned().collect()))
+        .collect()
+}
+
+/// Replays `plan` on `assignment` phase by phase, checking every copy goes to a node not holding
+/// the partition yet, and every switch and drop is on a node holding it.
+///
+/// Returns the replicas of each partition at each phase boundary, starting with `assignment`.
+pub fn replay(assignment: &Assignment, plan: &Plan) -> Vec<BTreeMap<Partition, BTreeSet<Node>>> {
+    let mut state = replica_sets(assignment);
+    let mut states = vec![state.clone()];
+    for phase in plan.phases() {
+        for step in &phase.steps {
+            let ns = state.entry(step.partition()).or_default();
+            match step {
+                Step::Copy { node, source, .. } => {
+                    if let Some(source) = source {
+                        assert!(ns.contains(source), "{step}, but the source has no replica");
+                    }
+                    assert!(ns.insert(node.clone()), "{step}, but the node has a replica already");
+                }
+                Step::Switch { from, to, .. } => {
+                    assert!(ns.contains(from) && ns.contains(to), "{step}, but missing a replica");
+                }
+                Step::Drop { node, .. } => {
+                    assert!(ns.remove(node), "{step}, but the node has no replica");
+                }
+            }
+        }
+        state.retain(|_p, ns| !ns.is_empty());
+        states.push(state.clone());
+    }
+
+    states
+}
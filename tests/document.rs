@@ -0,0 +1,79 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use assignment::{Assignment, Document, Topology};
+use common::{assert_rejected, nodes, run};
+
+fn document() -> Document {
+    let ns = nodes(&["a", "b", "c", "d", "e"]);
+    let tables = ["t1".to_string(), "t2".to_string()];
+    Document::init_with_topology(&tables, &ns, 12, 3, false, &Topology::default()).unwrap()
+}
+
+#[test]
+fn remove_with_replication_factor() {
+    let removes = nodes(&["a", "b"]);
+    let (removed, _moves) =
+        document().remove_nodes_with_topology(&removes, None, &Topology::default()).unwrap();
+    assert!(removed.tables().values().all(|a| a.replication_factor() == 3));
+
+    assert_rejected(
+        document().remove_nodes_with_topology(&removes, Some(4), &Topology::default()),
+        "NO less nodes then the replication factor",
+    );
+}
+
+#[test]
+fn cli_remove_all_tables_with_replication_factor() {
+    let input = serde_json::to_string(&document()).unwrap();
+    let output = run(&["remove", "-n", "a,b", "--all-tables", "-r", "4"], &input);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("NO less nodes"));
+
+    let output = run(&["remove", "-n", "a,b", "--all-tables", "-o", "json"], &input);
+    assert!(output.status.success());
+    let removed: Document = String::from_utf8_lossy(&output.stdout).parse().unwrap();
+    let assignment: &Assignment = removed.get("t1").unwrap();
+    assert_eq!(assignment.nodes().len(), 3);
+}
+
+/// The difference of the most and the least of `counts`.
+fn spread<'a>(counts: impl IntoIterator<Item = &'a usize> + Clone) -> usize {
+    let max = counts.clone().into_iter().max().copied().unwrap_or(0);
+    let min = counts.into_iter().min().copied().unwrap_or(0);
+    max - min
+}
+
+/// Checks every table stays balanced on its own, and the totals are balanced too.
+fn check_totals(document: &Document) {
+    for (name, assignment) in document.tables() {
+        let counts = assignment.nodes_map().values().map(|ps| ps.len()).collect::<Vec<_>>();
+        assert!(spread(&counts) <= 1, "{name}: {assignment:?}");
+    }
+    assert!(spread(document.totals().values()) <= 1, "{:?}", document.totals());
+}
+
+#[test]
+fn balance_totals_of_many_tables() {
+    let ns = nodes(&["a", "b", "c", "d", "e"]);
+    let topology = Topology::default();
+    // Each table alone is balanced, but the extra partitions are always on the first nodes
+    let tables = (1..=30)
+        .map(|i| (format!("t{i}"), Assignment::init(&ns, 7, 2, false).unwrap()))
+        .collect::<BTreeMap<_, _>>();
+    let document = Document::from(tables);
+    assert!(spread(document.totals().values()) > 1);
+
+    let (balanced, moves) = document.balance_totals(Default::default(), &topology);
+    assert!(moves.values().any(|ms| !ms.is_empty()));
+    check_totals(&balanced);
+
+    let tables = (1..=30).map(|i| format!("t{i}")).collect::<Vec<_>>();
+    for replication_factor in 1..=3 {
+        let document =
+            Document::init_with_topology(&tables, &ns, 7, replication_factor, false, &topology)
+                .unwrap();
+        check_totals(&document);
+    }
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use assignment::{
+    duplicates, Assignment, Document, GreedyStrategy, Node, OptimalStrategy, RendezvousStrategy,
+    Strategy, Topology,
+};
+use common::{assert_rejected, nodes, run};
+
+fn initial() -> Assignment {
+    Assignment::init(&nodes(&["a", "b", "c", "d"]), 12, 2, false).unwrap()
+}
+
+fn strategies() -> Vec<Box<dyn Strategy>> {
+    vec![Box::new(GreedyStrategy), Box::new(OptimalStrategy), Box::new(RendezvousStrategy)]
+}
+
+fn assert_duplicated<T>(result: anyhow::Result<T>, names: &str) {
+    assert_rejected(result, &format!("Provided duplicated nodes: {names}"));
+}
+
+#[test]
+fn duplicates_not_adjacent() {
+    let ns = nodes(&["a", "b", "a", "c", "b", "a"]);
+    assert_eq!(duplicates(&ns), vec![&Node::from("a"), &Node::from("b")]);
+    assert!(duplicates(&nodes(&["a", "b", "c"])).is_empty());
+}
+
+#[test]
+fn init_rejects_duplicates() {
+    let ns = nodes(&["a", "b", "c", "a"]);
+    assert_duplicated(Assignment::init(&ns, 12, 2, false), "a");
+    assert_duplicated(Assignment::init_rendezvous(&ns, 12, 2, false, &Topology::default()), "a");
+    for strategy in strategies() {
+        assert_duplicated(strategy.init(&ns, 12, 2, false, &Topology::default()), "a");
+    }
+
+    let tables = ["t1".to_string(), "t2".to_string()];
+    assert_duplicated(
+        Document::init_with_topology(&tables, &ns, 12, 2, false, &Topology::default()),
+        "a",
+    );
+}
+
+#[test]
+fn add_rejects_duplicates() {
+    let adds = nodes(&["e", "f", "e"]);
+    assert_duplicated(initial().add_nodes(adds.clone()), "e");
+    assert_duplicated(initial().add_nodes_optimal(&adds, &Topology::default()), "e");
+    for strategy in strategies() {
+        assert_duplicated(strategy.add(initial(), &adds, &Topology::default()), "e");
+    }
+
+    let document = [("t1".to_string(), initial())].into_iter().collect::<Document>();
+    assert_duplicated(document.add_nodes_with_topology(&adds, &Topology::default()), "e");
+}
+
+#[test]
+fn remove_rejects_duplicates() {
+    let removes = nodes(&["a", "b", "a"]);
+    assert_duplicated(initial().remove_nodes(&removes, 2), "a");
+    assert_duplicated(initial().remove_nodes_optimal(&removes, 2, &Topology::default()), "a");
+    for strategy in strategies() {
+        assert_duplicated(strategy.remove(initial(), &removes, 2, &Topology::default()), "a");
+    }
+
+    let document = [("t1".to_string(), initial())].into_iter().collect::<Document>();
+    assert_duplicated(
+        document.remove_nodes_with_topology(&removes, None, &Topology::default()),
+        "a",
+    );
+}
+
+#[test]
+fn report_finds_duplicated_replicas() {
+    let assignment: Assignment = r#"{"1": ["a", "b", "a"], "2": ["b", "c", "d"]}"#.parse().unwrap();
+    let report = assignment.report(1, 2, 3, &Topology::default(), false);
+    assert!(report.has_errors());
+    assert!(report
+        .violations()
+        .iter()
+        .any(|v| v.message == "Partition(1) replicas on duplicate nodes: a"));
+}
+
+#[test]
+fn cli_rejects_duplicates() {
+    let output = run(&["init", "-n", "a,b,c,a", "-p", "12", "-r", "2"], "");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Provided duplicated nodes: a"));
+
+    let input = serde_json::to_string(&initial()).unwrap();
+    for strategy in ["greedy", "optimal", "rendezvous"] {
+        let output = run(&["add", "-n", "e,f,e", "--strategy", strategy], &input);
+        assert!(!output.status.success());
+        assert!(String::from_utf8_lossy(&output.stderr).contains("Provided duplicated nodes: e"));
+    }
+
+    let tables = format!(r#"{{"t1": {input}, "t2": {input}}}"#);
+    let output = run(&["add", "-n", "e,f,e", "--all-tables"], &tables);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Provided duplicated nodes: e"));
+
+    let output = run(&["remove", "-n", "a,b,a", "-r", "2"], &input);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Provided duplicated nodes: a"));
+
+    let input = r#"{"1": ["a", "b", "a"], "2": ["b", "c", "d"]}"#;
+    let output = run(&["validate", "-p", "2", "-r", "3", "-o", "json"], input);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .contains("Partition(1) replicas on duplicate nodes: a"));
+}
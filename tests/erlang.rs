@@ -0,0 +1,34 @@
+mod common;
+
+use assignment::{frag_index, frag_table, Assignment, Partition};
+use common::{nodes, run};
+
+#[test]
+fn fragments_count_from_first_id() {
+    assert_eq!(frag_index(Partition::new(1), 1), 1);
+    assert_eq!(frag_index(Partition::new(0), 0), 1);
+    assert_eq!(frag_table("tab", Partition::new(1), 1), "'tab'");
+    assert_eq!(frag_table("tab", Partition::new(3), 1), "'tab_frag3'");
+    assert_eq!(frag_table("tab", Partition::new(0), 0), "'tab'");
+    assert_eq!(frag_table("tab", Partition::new(3), 0), "'tab_frag4'");
+
+    let assignment = Assignment::init(&nodes(&["a", "b", "c"]), 3, 2, true).unwrap();
+    let erlang = assignment.to_erlang();
+    assert!(erlang.contains("#{frag_index => 1, instances => ['a', 'b']}"), "{erlang}");
+    assert!(erlang.contains("#{frag_index => 3,"), "{erlang}");
+    assert!(!erlang.contains("frag_index => 0"), "{erlang}");
+}
+
+#[test]
+fn mnesia_script_starts_with_zero() {
+    let assignment = Assignment::init(&nodes(&["a", "b", "c"]), 4, 2, true).unwrap();
+    let input = serde_json::to_string(&assignment).unwrap();
+    let output = run(&["add", "-n", "d", "--table", "tab", "-o", "mnesia-script"], &input);
+    assert!(output.status.success());
+    let script = String::from_utf8_lossy(&output.stdout);
+    assert!(!script.contains("tab_frag0"), "{script}");
+    assert!(!script.contains("tab_frag5"), "{script}");
+    // Partition 0 is the base table, and partition 2 is the third fragment
+    assert!(script.contains("mnesia:add_table_copy('tab', 'd', disc_copies)"), "{script}");
+    assert!(script.contains("mnesia:add_table_copy('tab_frag3', 'd', disc_copies)"), "{script}");
+}
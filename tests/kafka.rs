@@ -0,0 +1,24 @@
+mod common;
+
+use assignment::{Assignment, KafkaReassignment};
+use common::{nodes, run};
+
+#[test]
+fn single_topic_is_kept() {
+    let assignment = Assignment::init(&nodes(&["1", "2", "3"]), 6, 2, false).unwrap();
+    let input = KafkaReassignment::to_json([("events", &assignment)]).unwrap();
+    for args in [
+        &["add", "-n", "4"][..],
+        &["remove", "-n", "3", "-r", "2"],
+        &["rebalance"],
+        &["validate", "-p", "6", "-r", "2"],
+    ] {
+        let args = [args, &["--input-format", "kafka", "-o", "kafka"]].concat();
+        let output = run(&args, &input);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{args:?}: {stderr}");
+
+        let reassignment = String::from_utf8_lossy(&output.stdout).parse::<KafkaReassignment>();
+        assert_eq!(reassignment.unwrap().topics(), ["events"], "{args:?}");
+    }
+}
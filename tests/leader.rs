@@ -0,0 +1,53 @@
+mod common;
+
+use assignment::{Action, Assignment, GreedyStrategy, Strategy, Topology};
+use common::{nodes, replica_sets};
+
+/// Balances the leaders of `assignment`, and checks only the order of the replicas changes, every
+/// node leads a number of partitions within its target, and every promotion is between two nodes
+/// holding the partition.
+fn check(assignment: &Assignment, topology: &Topology) {
+    let (balanced, actions) = assignment.clone().balance_leaders(topology);
+    assert_eq!(replica_sets(&balanced), replica_sets(assignment));
+
+    let targets = balanced.leader_targets(topology);
+    for (n, ps) in balanced.leaders() {
+        let target = &targets[&n];
+        assert!(
+            target.lower <= ps.len() && ps.len() <= target.upper,
+            "{n} leads {} vs {target:?} in {topology:?}",
+            ps.len(),
+        );
+    }
+
+    for action in &actions {
+        let Action::PromoteLeader { partition, from, to } = action else {
+            panic!("{action:?} is not a promotion");
+        };
+        let ns = assignment.get(partition).unwrap();
+        assert!(from != to && ns.contains(from) && ns.contains(to), "{action:?} on {ns:?}");
+    }
+}
+
+#[test]
+fn balance_leaders() {
+    let all = nodes(&["a", "b", "c", "d", "e", "f"]);
+    let weights = Topology::new().with_weight(all[0].clone(), 2).with_weight(all[1].clone(), 3);
+    for topology in [Topology::default(), weights] {
+        for (partitions, replication_factor) in [(12, 2), (30, 3), (61, 3)] {
+            let assignment = GreedyStrategy
+                .init(&all, partitions, replication_factor, false, &topology)
+                .unwrap();
+            check(&assignment, &topology);
+
+            // The leaders of the removed node go to the next replicas, which skews them
+            let (removed, _moves) = GreedyStrategy
+                .remove(assignment, &all[5..], replication_factor, &topology)
+                .unwrap();
+            check(&removed, &topology);
+
+            let (added, _moves) = GreedyStrategy.add(removed, &all[5..], &topology).unwrap();
+            check(&added, &topology);
+        }
+    }
+}
@@ -0,0 +1,285 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use assignment::{Assignment, GreedyStrategy, Move, Node, Partition, Strategy, Topology};
+use common::{nodes, replica_sets};
+
+/// A deterministic pseudo random sequence, so the failures are reproducible.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, n: usize) -> usize {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (self.0 >> 33) as usize % n
+    }
+
+    /// A random assignment of `partitions` partitions with `replication_factor` replicas each on
+    /// distinct `nodes`, regardless of the balance.
+    fn assignment(&mut self, nodes: &[Node], partitions: u32, replication_factor: usize)
+    -> Assignment
+    {
+        (1..=partitions)
+            .map(|p| {
+                let mut ns = nodes.to_vec();
+                let chosen = (0..replication_factor)
+                    .map(|_| ns.remove(self.next(ns.len())))
+                    .collect();
+                (Partition::new(p), chosen)
+            })
+            .collect()
+    }
+}
+
+fn counts(assignment: &Assignment) -> BTreeMap<Node, usize> {
+    assignment.nodes_map().into_iter().map(|(n, ps)| (n, ps.len())).collect()
+}
+
+/// Checks every partition keeps its replicas on distinct nodes of `nodes`, spread across as many
+/// zones as possible, and every node is within its target.
+fn check(after: &Assignment, before: &Assignment, nodes: &[Node], topology: &Topology) {
+    let sets = replica_sets(after);
+    for (p, ns) in after.partitions() {
+        assert_eq!(sets[p].len(), before.get(p).unwrap().len(), "{p}: {ns:?}");
+        assert!(ns.iter().all(|n| nodes.contains(n)), "{p}: {ns:?}");
+    }
+    assert!(after.zone_violations(topology).is_empty(), "{:?}", after.zone_violations(topology));
+
+    let replicas = before.partitions().values().map(|ns| ns.len()).sum();
+    let targets = topology.targets(nodes, replicas, before.len());
+    let counts = counts(after);
+    for (n, target) in &targets {
+        let count = counts.get(n).copied().unwrap_or(0);
+        assert!(
+            target.lower <= count && count <= target.upper,
+            "{n} has {count} partitions, but the target is {target:?}",
+        );
+    }
+}
+
+/// Checks each of the `moves` places a replica on a node not holding it before, which stays
+/// there, i.e. no replica moves twice.
+fn check_moves(moves: &[Move], before: &Assignment, after: &Assignment) {
+    let (before, after) = (replica_sets(before), replica_sets(after));
+    let placed = after.iter().map(|(p, ns)| ns.difference(&before[p]).count()).sum::<usize>();
+    assert_eq!(moves.len(), placed, "{moves:?}");
+}
+
+/// The minimum number of moves to get `before` within the targets of `nodes` with the replicas
+/// spread across as many zones as possible, by exhaustive search, or `None` if impossible.
+fn exact(before: &Assignment, nodes: &[Node], topology: &Topology) -> Option<usize> {
+    struct Search<'a> {
+        partitions: Vec<&'a Vec<Node>>,
+        nodes: &'a [Node],
+        topology: &'a Topology,
+        zones_count: usize,
+        lower: Vec<usize>,
+        upper: Vec<usize>,
+        counts: Vec<usize>,
+        best: Option<usize>,
+    }
+
+    impl Search<'_> {
+        /// Chooses the replicas of the `i`th partition on, with `cost` moves so far.
+        fn partition(&mut self, i: usize, cost: usize) {
+            if self.best.is_some_and(|best| cost >= best) {
+                return;
+            }
+            if i == self.partitions.len() {
+                if self.counts.iter().zip(&self.lower).all(|(c, l)| c >= l) {
+                    self.best = Some(cost);
+                }
+                return;
+            }
+            self.replica(i, 0, &mut vec![], cost);
+        }
+
+        /// Chooses the rest replicas of the `i`th partition from the `from`th node on.
+        fn replica(&mut self, i: usize, from: usize, chosen: &mut Vec<usize>, cost: usize) {
+            let ns = self.partitions[i];
+            if chosen.len() == ns.len() {
+                let replicas = chosen.iter().map(|&j| self.nodes[j].clone()).collect::<Vec<_>>();
+                if self.topology.is_spread(&replicas, self.zones_count) {
+                    let cost = cost + replicas.iter().filter(|n| !ns.contains(n)).count();
+                    self.partition(i + 1, cost);
+                }
+                return;
+            }
+
+            for j in from..self.nodes.len() {
+                if self.counts[j] < self.upper[j] {
+                    self.counts[j] += 1;
+                    chosen.push(j);
+                    self.replica(i, j + 1, chosen, cost);
+                    chosen.pop();
+                    self.counts[j] -= 1;
+                }
+            }
+        }
+    }
+
+    let replicas = before.partitions().values().map(|ns| ns.len()).sum();
+    let targets = topology.targets(nodes, replicas, before.len());
+    let mut search = Search {
+        partitions: before.partitions().values().collect(),
+        nodes,
+        topology,
+        zones_count: topology.zones(nodes).len(),
+        lower: nodes.iter().map(|n| targets[n].lower).collect(),
+        upper: nodes.iter().map(|n| targets[n].upper).collect(),
+        counts: vec![0; nodes.len()],
+        best: None,
+    };
+    search.partition(0, 0);
+    search.best
+}
+
+#[test]
+fn fewer_moves_than_greedy() {
+    // The scenario of `test.sh`
+    let all = nodes(&["node_1", "node_2", "node_3", "node_4", "node_5"]);
+    let topology = Topology::default();
+    let mut assignment = Assignment::init(&all, 60, 3, false).unwrap();
+    let mut rng = Lcg(7);
+    for _ in 0..50 {
+        let removes = [all[rng.next(all.len())].clone()];
+        let remains = all.iter().filter(|n| !removes.contains(n)).cloned().collect::<Vec<_>>();
+
+        let (_greedy, greedy_moves) =
+            GreedyStrategy.remove(assignment.clone(), &removes, 3, &topology).unwrap();
+        let (removed, moves) =
+            assignment.clone().remove_nodes_optimal(&removes, 3, &topology).unwrap();
+        check(&removed, &assignment, &remains, &topology);
+        check_moves(&moves, &assignment, &removed);
+        assert!(moves.len() <= greedy_moves.len(), "{} > {}", moves.len(), greedy_moves.len());
+
+        let (_greedy, greedy_moves) =
+            GreedyStrategy.add(removed.clone(), &removes, &topology).unwrap();
+        let (added, moves) = removed.clone().add_nodes_optimal(&removes, &topology).unwrap();
+        check(&added, &removed, &all, &topology);
+        check_moves(&moves, &removed, &added);
+        assert!(moves.len() <= greedy_moves.len(), "{} > {}", moves.len(), greedy_moves.len());
+
+        assignment = added;
+    }
+}
+
+#[test]
+fn minimal_moves() {
+    let all = nodes(&["n1", "n2", "n3", "n4", "n5"]);
+    let weighted = Topology::new().with_weight(all[0].clone(), 3).with_weight(all[1].clone(), 2);
+    let zoned = all
+        .iter()
+        .enumerate()
+        .fold(Topology::new(), |t, (i, n)| t.with_zone(n.clone(), format!("az{}", i % 3)));
+    let mut rng = Lcg(3);
+    for topology in [Topology::default(), weighted, zoned] {
+        for _ in 0..20 {
+            // Rebalance as is, after removing a node, and after adding one
+            let before = rng.assignment(&all, 6, 2);
+            let remove = all[rng.next(all.len())].clone();
+            let remains = all.iter().filter(|&n| *n != remove).cloned().collect::<Vec<_>>();
+            let before_add = rng.assignment(&remains, 6, 2);
+            for (before, nodes) in [(&before, &all), (&before, &remains), (&before_add, &all)] {
+                let Some(minimum) = exact(before, nodes, &topology) else {
+                    continue;
+                };
+                let (after, moves) = before.clone().rebalance_optimal(nodes, &topology).unwrap();
+                check(&after, before, nodes, &topology);
+                check_moves(&moves, before, &after);
+                assert_eq!(moves.len(), minimum, "{before:?} on {nodes:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn weighted_nodes() {
+    let all = nodes(&["n1", "n2", "n3", "n4", "n5", "n6"]);
+    let topology = Topology::new()
+        .with_weight(all[0].clone(), 3)
+        .with_weight(all[1].clone(), 2)
+        .with_weight(all[2].clone(), 2);
+    let mut rng = Lcg(11);
+    for (partitions, replication_factor) in [(12, 2), (30, 3), (64, 3), (17, 1)] {
+        let initial = Assignment::init(&all, partitions, replication_factor, false).unwrap();
+        let (assignment, moves) = initial.clone().rebalance_optimal(&all, &topology).unwrap();
+        check(&assignment, &initial, &all, &topology);
+        check_moves(&moves, &initial, &assignment);
+
+        let removes = [all[rng.next(all.len())].clone()];
+        let remains = all.iter().filter(|n| !removes.contains(n)).cloned().collect::<Vec<_>>();
+        let (removed, moves) = assignment
+            .clone()
+            .remove_nodes_optimal(&removes, replication_factor, &topology)
+            .unwrap();
+        check(&removed, &assignment, &remains, &topology);
+        check_moves(&moves, &assignment, &removed);
+
+        let (added, moves) = removed.clone().add_nodes_optimal(&removes, &topology).unwrap();
+        check(&added, &removed, &all, &topology);
+        check_moves(&moves, &removed, &added);
+    }
+}
+
+#[test]
+fn zones_no_less_than_replication_factor() {
+    let all = nodes(&["n1", "n2", "n3", "n4", "n5", "n6", "n7", "n8", "n9"]);
+    let topology = all
+        .iter()
+        .enumerate()
+        .fold(Topology::new(), |t, (i, n)| t.with_zone(n.clone(), format!("az{}", i % 3)));
+    for (partitions, replication_factor) in [(12, 2), (30, 3), (60, 3)] {
+        let initial = Assignment::init(&all[..6], partitions, replication_factor, false).unwrap();
+        let (assignment, moves) = initial.clone().rebalance_optimal(&all[..6], &topology).unwrap();
+        check(&assignment, &initial, &all[..6], &topology);
+        check_moves(&moves, &initial, &assignment);
+
+        let adds = &all[6..];
+        let (_greedy, greedy_moves) =
+            GreedyStrategy.add(assignment.clone(), adds, &topology).unwrap();
+        let (added, moves) = assignment.clone().add_nodes_optimal(adds, &topology).unwrap();
+        check(&added, &assignment, &all, &topology);
+        check_moves(&moves, &assignment, &added);
+        assert!(moves.len() <= greedy_moves.len(), "{} > {}", moves.len(), greedy_moves.len());
+
+        // One of each zone, otherwise the zones may leave no way to keep within the targets
+        let removes = [all[0].clone(), all[4].clone(), all[8].clone()];
+        let remains = all.iter().filter(|n| !removes.contains(n)).cloned().collect::<Vec<_>>();
+        let (_greedy, greedy_moves) =
+            GreedyStrategy.remove(added.clone(), &removes, replication_factor, &topology).unwrap();
+        let (removed, moves) = added
+            .clone()
+            .remove_nodes_optimal(&removes, replication_factor, &topology)
+            .unwrap();
+        check(&removed, &added, &remains, &topology);
+        check_moves(&moves, &added, &removed);
+        assert!(moves.len() <= greedy_moves.len(), "{} > {}", moves.len(), greedy_moves.len());
+    }
+}
+
+#[test]
+fn zones_less_than_replication_factor() {
+    // Each partition of 3 replicas spans both zones, with 2 replicas in one of them
+    let all = nodes(&["n1", "n2", "n3", "n4", "n5", "n6", "n7"]);
+    let topology = all
+        .iter()
+        .enumerate()
+        .fold(Topology::new(), |t, (i, n)| t.with_zone(n.clone(), format!("az{}", i % 2)))
+        .with_weight(all[0].clone(), 2);
+    for partitions in [12, 30, 60] {
+        let initial = Assignment::init(&all[..6], partitions, 3, false).unwrap();
+        let (assignment, _moves) = initial.clone().rebalance_optimal(&all[..6], &topology).unwrap();
+        check(&assignment, &initial, &all[..6], &topology);
+
+        let adds = &all[6..];
+        let (added, _moves) = assignment.clone().add_nodes_optimal(adds, &topology).unwrap();
+        check(&added, &assignment, &all, &topology);
+
+        // Keeping the weights of both zones close, so the targets are feasible
+        let removes = [all[2].clone()];
+        let remains = all.iter().filter(|n| !removes.contains(n)).cloned().collect::<Vec<_>>();
+        let (removed, _moves) = added.clone().remove_nodes_optimal(&removes, 3, &topology).unwrap();
+        check(&removed, &added, &remains, &topology);
+    }
+}
@@ -0,0 +1,132 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use assignment::{
+    Action, Assignment, GreedyStrategy, Move, Node, OptimalStrategy, Plan, RendezvousStrategy,
+    Strategy, Topology,
+};
+use common::{nodes, replay, replica_sets};
+
+/// Plans the `actions` from `before` to `after` with each limit, and checks no partition kept by
+/// both ever has fewer replicas than in both of them at any phase boundary, no phase touches any
+/// node more than the limit, and the plan ends up with `after`.
+fn check(before: &Assignment, after: &Assignment, actions: &[Action]) {
+    for max_per_node in [None, Some(1), Some(2), Some(5)] {
+        let plan = Plan::new(actions, max_per_node);
+        let states = replay(before, &plan);
+        for state in &states {
+            for (p, ns) in state {
+                let (Some(b), Some(a)) = (before.get(p), after.get(p)) else {
+                    continue;
+                };
+                assert!(
+                    ns.len() >= b.len().min(a.len()),
+                    "{p} has only {} replicas with {max_per_node:?} per node in {plan:?}",
+                    ns.len(),
+                );
+            }
+        }
+        assert_eq!(states.last().unwrap(), &replica_sets(after), "{plan:?}");
+
+        if let Some(max) = max_per_node {
+            for phase in plan.phases() {
+                let mut loads: BTreeMap<&Node, usize> = Default::default();
+                for n in phase.steps.iter().flat_map(|s| s.nodes()) {
+                    *loads.entry(n).or_default() += 1;
+                }
+                assert!(loads.values().all(|&l| l <= max), "{loads:?} over {max} in {phase:?}");
+            }
+        }
+    }
+}
+
+fn check_moves(before: &Assignment, after: &Assignment, moves: Vec<Move>) {
+    check(before, after, &moves.into_iter().map(Action::from).collect::<Vec<_>>());
+}
+
+#[test]
+fn strategies_keep_replicas() {
+    let all = nodes(&["n1", "n2", "n3", "n4", "n5", "n6", "n7"]);
+    let topology = all
+        .iter()
+        .enumerate()
+        .fold(Topology::new(), |t, (i, n)| t.with_zone(n.clone(), format!("az{}", i % 3)));
+    let strategies: [&dyn Strategy; 3] = [&GreedyStrategy, &OptimalStrategy, &RendezvousStrategy];
+    for strategy in strategies {
+        for topology in [&Topology::default(), &topology] {
+            for (partitions, replication_factor) in [(12, 1), (30, 2), (60, 3)] {
+                let assignment = strategy
+                    .init(&all[..5], partitions, replication_factor, false, topology)
+                    .unwrap();
+
+                let (added, moves) = strategy.add(assignment.clone(), &all[5..], topology).unwrap();
+                check_moves(&assignment, &added, moves);
+
+                let removes = [all[0].clone(), all[6].clone()];
+                let (removed, moves) = strategy
+                    .remove(added.clone(), &removes, replication_factor, topology)
+                    .unwrap();
+                check_moves(&added, &removed, moves);
+
+                let (rebalanced, moves) = strategy.rebalance(removed.clone(), topology).unwrap();
+                check_moves(&removed, &rebalanced, moves);
+            }
+        }
+    }
+}
+
+#[test]
+fn replace_node_keeps_replicas() {
+    let all = nodes(&["n1", "n2", "n3", "n4"]);
+    let assignment = Assignment::init(&all, 12, 3, false).unwrap();
+    let (replaced, moves) = assignment.clone().replace_node(&all[1], &Node::from("n5")).unwrap();
+    check_moves(&assignment, &replaced, moves);
+}
+
+#[test]
+fn replication_factor_keeps_replicas() {
+    // Dropping n6 from partition 1 and then moving n2 to n6 to balance must not drop both
+    let assignment: Assignment = r#"{"1": ["n2", "n6"], "2": ["n2", "n3"], "3": ["n3", "n4"],
+        "4": ["n4", "n5"], "5": ["n5", "n6"]}"#.parse().unwrap();
+    let (after, actions) =
+        assignment.clone().set_replication_factor(1, &Topology::default()).unwrap();
+    check(&assignment, &after, &actions);
+
+    let all = nodes(&["n1", "n2", "n3", "n4", "n5"]);
+    for from in 1..=3 {
+        let assignment = Assignment::init(&all, 17, from, false).unwrap();
+        for to in 1..=all.len() {
+            let (after, actions) =
+                assignment.clone().set_replication_factor(to, &Topology::default()).unwrap();
+            check(&assignment, &after, &actions);
+        }
+    }
+}
+
+#[test]
+fn partitions_keep_replicas() {
+    let all = nodes(&["n1", "n2", "n3", "n4", "n5"]);
+    let topology = Topology::default();
+    for replication_factor in 1..=3 {
+        let assignment = Assignment::init(&all, 12, replication_factor, false).unwrap();
+        for count in [1, 5, 13] {
+            let (added, actions) = assignment.clone().add_partitions(count, &topology).unwrap();
+            check(&assignment, &added, &actions);
+        }
+        for count in [1, 5, 11] {
+            let (removed, actions) = assignment.clone().remove_partitions(count, &topology).unwrap();
+            check(&assignment, &removed, &actions);
+        }
+    }
+}
+
+#[test]
+fn leaders_keep_replicas() {
+    let all = nodes(&["n1", "n2", "n3", "n4", "n5"]);
+    let topology = Topology::default();
+    let assignment = Assignment::init(&all, 12, 3, false).unwrap();
+    let (removed, _moves) = assignment.remove_nodes(&all[..1], 3).unwrap();
+    let (balanced, actions) = removed.clone().balance_leaders(&topology);
+    check(&removed, &balanced, &actions);
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use assignment::{Assignment, Document, Node, NodePolicy, Topology};
+use common::{assert_rejected, nodes, run};
+
+#[test]
+fn default_keeps_names() {
+    let policy = NodePolicy::new();
+    let ns = nodes(&[" node_1", "Node_1", "node_1"]);
+    assert_eq!(policy.normalize_all(&ns).unwrap(), ns);
+    assert_rejected(policy.normalize(&Node::from("")), "Invalid node name ``, which is empty");
+}
+
+#[test]
+fn trim_and_fold_case() {
+    let policy = NodePolicy::new().with_trim(true).with_fold_case(true);
+    assert_eq!(policy.normalize(&Node::from(" Node_1\t")).unwrap(), Node::from("node_1"));
+    assert_rejected(policy.normalize(&Node::from("  ")), "Invalid node name `  `, which is empty");
+
+    let ns = nodes(&["a", "b", "a"]);
+    assert_eq!(policy.normalize_all(&ns).unwrap(), ns);
+    assert_rejected(
+        policy.normalize_all(&nodes(&["node_1", "b", " NODE_1"])),
+        "Node names `node_1` and ` NODE_1` collide as `node_1`",
+    );
+}
+
+#[test]
+fn pattern_matches_whole_name() {
+    let policy = NodePolicy::new().with_fold_case(true).with_pattern("[a-z0-9_]+").unwrap();
+    assert_eq!(policy.normalize(&Node::from("Node_1")).unwrap(), Node::from("node_1"));
+    assert_rejected(
+        policy.normalize(&Node::from("node-1")),
+        "Invalid node name `node-1`, which doesn't match",
+    );
+    assert_rejected(NodePolicy::new().with_pattern("("), "Invalid node name pattern `(`");
+}
+
+#[test]
+fn normalize_inputs() {
+    let policy = NodePolicy::new().with_trim(true).with_fold_case(true);
+    let assignment: Assignment = r#"{"1": ["A", " b"], "2": [" b", "c"]}"#.parse().unwrap();
+    let expected: Assignment = r#"{"1": ["a", "b"], "2": ["b", "c"]}"#.parse().unwrap();
+    assert_eq!(assignment.normalize(&policy).unwrap(), expected);
+
+    let assignment: Assignment = r#"{"1": ["a", "b"], "2": ["B", "c"]}"#.parse().unwrap();
+    assert_rejected(assignment.normalize(&policy), "Node names `b` and `B` collide as `b`");
+
+    // The same node must have the same name across the tables
+    let document: Document =
+        r#"{"t1": {"1": ["a", "b"]}, "t2": {"1": ["A", "b"]}}"#.parse().unwrap();
+    assert_rejected(document.normalize(&policy), "Node names `a` and `A` collide as `a`");
+
+    let topology: Topology = r#"{"A": {"zone": "az1"}, "b": {"zone": "az2"}}"#.parse().unwrap();
+    let expected: Topology = r#"{"a": {"zone": "az1"}, "b": {"zone": "az2"}}"#.parse().unwrap();
+    assert_eq!(topology.normalize(&policy).unwrap(), expected);
+}
+
+#[test]
+fn cli_normalizes_names() {
+    let output = run(&["init", "-n", "a,,b", "-p", "4", "-r", "2"], "");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("Invalid node name ``, which is empty"));
+
+    let args = ["init", "-n", " A,b, C", "-p", "4", "-r", "2", "-o", "json"];
+    let output = run(&[&args[..], &["--trim-node-names", "--lowercase-node-names"]].concat(), "");
+    assert!(output.status.success());
+    let assignment: Assignment = String::from_utf8_lossy(&output.stdout).parse().unwrap();
+    let names = assignment.nodes().into_iter().cloned().collect::<Vec<_>>();
+    assert_eq!(names, nodes(&["a", "b", "c"]));
+
+    let input = r#"{"1": ["a", "b"], "2": ["b", "c"]}"#;
+    let output = run(&["add", "-n", "A", "--lowercase-node-names"], input);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("already exists"));
+
+    let output = run(&["remove", "-n", "c$", "-r", "2", "--node-name-pattern", "[a-z]+"], input);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid node name `c$`"));
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use assignment::{
+    Assignment, GreedyStrategy, OptimalStrategy, RendezvousStrategy, Strategy, Topology,
+};
+use common::{assert_rejected, nodes, run};
+
+#[test]
+fn replication_factor_less_than_actual() {
+    let topology = Topology::default();
+    let strategies: [&dyn Strategy; 3] = [&GreedyStrategy, &OptimalStrategy, &RendezvousStrategy];
+    // Too few nodes left for the 3 replicas, even if the given replication factor is 2
+    let cases = [
+        (nodes(&["a", "b", "c", "d"]), nodes(&["c", "d"])),
+        (nodes(&["a", "b", "c"]), nodes(&["c"])),
+    ];
+    for strategy in strategies {
+        for (all, removes) in &cases {
+            let assignment = Assignment::init(all, 12, 3, false).unwrap();
+            assert_rejected(
+                strategy.remove(assignment, removes, 2, &topology),
+                "NO less nodes then the replication factor",
+            );
+        }
+    }
+
+    let assignment = Assignment::init(&cases[0].0, 12, 3, false).unwrap();
+    let input = serde_json::to_string(&assignment).unwrap();
+    let output = run(&["remove", "-n", "c,d", "-r", "2"], &input);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("NO less nodes then the replication factor"), "{stderr}");
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use assignment::{Assignment, Node, Topology};
+use common::{nodes, replica_sets, run};
+
+/// The topologies of `all`: none, weights, zones, and both with the same weight in each zone.
+fn topologies(all: &[Node]) -> [Topology; 4] {
+    let weights = all.iter().step_by(2).fold(Topology::new(), |t, n| t.with_weight(n.clone(), 2));
+    let zones = |t: Topology| {
+        all.iter().enumerate().fold(t, |t, (i, n)| t.with_zone(n.clone(), format!("az{}", i / 2)))
+    };
+    [Topology::default(), weights.clone(), zones(Topology::new()), zones(weights)]
+}
+
+/// Checks every node of `nodes` holds a number of partitions within its target, and the replicas
+/// of every partition span as many zones as possible.
+fn check_balance(assignment: &Assignment, nodes: &[Node], topology: &Topology) {
+    let replicas = assignment.partitions().values().map(|ns| ns.len()).sum();
+    let targets = topology.targets(nodes, replicas, assignment.len());
+    let nodes_map = assignment.nodes_map();
+    for n in nodes {
+        let count = nodes_map.get(n).map_or(0, |ps| ps.len());
+        let target = &targets[n];
+        assert!(target.lower <= count && count <= target.upper, "{n}: {count} vs {target:?}");
+    }
+    assert!(assignment.zone_violations(topology).is_empty(), "{topology:?}");
+}
+
+#[test]
+fn remove_then_add_restores() {
+    let all = nodes(&["a", "b", "c", "d", "e", "f"]);
+    for topology in &topologies(&all) {
+        for replication_factor in 1..=3 {
+            let assignment =
+                Assignment::init_rendezvous(&all, 60, replication_factor, false, topology).unwrap();
+            for (i, n) in all.iter().enumerate() {
+                let remains = [&all[..i], &all[i + 1..]].concat();
+                let (removed, _moves) =
+                    assignment.clone().rebalance_rendezvous(&remains, topology).unwrap();
+                assert!(!removed.nodes().contains(&n));
+
+                let (added, _moves) = removed.rebalance_rendezvous(&all, topology).unwrap();
+                assert_eq!(replica_sets(&added), replica_sets(&assignment), "{n} in {topology:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn recomputed_from_nodes() {
+    let all = nodes(&["a", "b", "c", "d", "e", "f"]);
+    for topology in &topologies(&all) {
+        let assignment = Assignment::init_rendezvous(&all, 60, 3, false, topology).unwrap();
+        let remains = nodes(&["a", "b", "c", "d", "f"]);
+        let (removed, _moves) = assignment.rebalance_rendezvous(&remains, topology).unwrap();
+        let init = Assignment::init_rendezvous(&remains, 60, 3, false, topology).unwrap();
+        assert_eq!(replica_sets(&removed), replica_sets(&init), "{topology:?}");
+    }
+
+    let init = |names| {
+        let args = ["init", "-n", names, "-p", "60", "-r", "3", "--strategy", "rendezvous"];
+        let output = run(&[&args[..], &["-o", "json"]].concat(), "");
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+    let args = ["remove", "-n", "e", "-r", "3", "--strategy", "rendezvous", "-o", "json"];
+    let output = run(&args, &init("a,b,c,d,e,f"));
+    assert!(output.status.success());
+    let removed = String::from_utf8_lossy(&output.stdout).parse::<Assignment>().unwrap();
+    let recomputed = init("a,b,c,d,f").parse::<Assignment>().unwrap();
+    assert_eq!(replica_sets(&removed), replica_sets(&recomputed));
+}
+
+#[test]
+fn balanced_with_weights_and_zones() {
+    let all = nodes(&["a", "b", "c", "d", "e", "f"]);
+    for topology in &topologies(&all) {
+        for (partitions, replication_factor) in [(12, 1), (30, 2), (60, 3), (61, 3)] {
+            let assignment =
+                Assignment::init_rendezvous(&all, partitions, replication_factor, false, topology)
+                    .unwrap();
+            check_balance(&assignment, &all, topology);
+
+            let remains = nodes(&["a", "b", "c", "d"]);
+            let (removed, _moves) =
+                assignment.rebalance_rendezvous(&remains, topology).unwrap();
+            check_balance(&removed, &remains, topology);
+        }
+    }
+}
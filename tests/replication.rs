@@ -0,0 +1,70 @@
+mod common;
+
+use assignment::{Action, Assignment, Node, Partition, Plan, Topology};
+use common::{nodes, replay, replica_sets};
+
+/// Changes the replication factor of `assignment`, and checks the plan of the actions never
+/// leaves any partition with fewer replicas than both the old and the new replication factor.
+fn check_set_replication_factor(assignment: Assignment, replication_factor: usize) {
+    let before = assignment.replication_factor();
+    let (after, actions) =
+        assignment.clone().set_replication_factor(replication_factor, &Topology::default()).unwrap();
+    let states = replay(&assignment, &Plan::new(&actions, None));
+    for state in &states {
+        for (p, ns) in state {
+            assert!(
+                ns.len() >= before.min(replication_factor),
+                "{p} has only {} replicas in the plan of {actions:?}",
+                ns.len(),
+            );
+        }
+    }
+    assert_eq!(states.last().unwrap(), &replica_sets(&after), "actions: {actions:?}");
+}
+
+#[test]
+fn shrink_keeps_replicas_moved_back() {
+    // Dropping n6 from partition 1 and then moving n2 to n6 to balance must not drop both
+    let assignment: Assignment = r#"{"1": ["n2", "n6"], "2": ["n2", "n3"], "3": ["n3", "n4"],
+        "4": ["n4", "n5"], "5": ["n5", "n6"]}"#.parse().unwrap();
+    let (after, actions) =
+        assignment.clone().set_replication_factor(1, &Topology::default()).unwrap();
+    assert!(actions.iter().all(|a| matches!(a, Action::DropReplica { .. })), "{actions:?}");
+    assert_eq!(after.get(&Partition::new(1)).unwrap(), &nodes(&["n6"]));
+    check_set_replication_factor(assignment, 1);
+}
+
+#[test]
+fn change_replication_factor_safely() {
+    let all = nodes(&["n1", "n2", "n3", "n4", "n5", "n6", "n7"]);
+    for count in 3..=all.len() {
+        for partitions in [5, 12, 17] {
+            for from in 1..=3 {
+                let assignment = Assignment::init(&all[..count], partitions, from, false).unwrap();
+                for to in 1..=assignment.nodes().len() {
+                    check_set_replication_factor(assignment.clone(), to);
+                }
+
+                if count > from {
+                    let removes = [Node::from("n1")];
+                    let (removed, _moves) = assignment.remove_nodes(&removes, from).unwrap();
+                    for to in 1..=removed.nodes().len() {
+                        check_set_replication_factor(removed.clone(), to);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn plan_folds_moves_to_dropped_replicas() {
+    let p = Partition::new(1);
+    let actions = [
+        Action::DropReplica { partition: p, node: Node::from("n6") },
+        Action::MoveReplica { partition: p, from: Node::from("n2"), to: Node::from("n6") },
+    ];
+    let assignment: Assignment = r#"{"1": ["n2", "n6"]}"#.parse().unwrap();
+    let states = replay(&assignment, &Plan::new(&actions, None));
+    assert_eq!(states.last().unwrap()[&p], nodes(&["n6"]).into_iter().collect());
+}
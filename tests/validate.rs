@@ -0,0 +1,60 @@
+mod common;
+
+use assignment::{Assignment, Partition};
+use common::{nodes, run};
+
+fn initial(starts_with_zero: bool) -> Assignment {
+    let ns = nodes(&["a", "b", "c", "d", "e"]);
+    Assignment::init(&ns, 60, 3, starts_with_zero).unwrap()
+}
+
+/// `assignment` without `partition`.
+fn without(assignment: Assignment, partition: u32) -> Assignment {
+    let mut partitions = assignment.into_inner();
+    partitions.remove(&Partition::new(partition));
+    partitions.into()
+}
+
+#[test]
+fn first_id_is_only_the_base() {
+    assert_eq!(initial(false).first_id(), 1);
+    assert_eq!(initial(true).first_id(), 0);
+    assert_eq!(without(initial(false), 1).first_id(), 1);
+    assert_eq!(without(initial(true), 1).first_id(), 0);
+}
+
+#[test]
+fn missing_first_partition() {
+    let input = serde_json::to_string(&without(initial(false), 1)).unwrap();
+    let output = run(&["validate", "-p", "60", "-r", "3", "-o", "json"], &input);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Partition(1) is missing"), "{stdout}");
+    assert!(!stdout.contains("Partition(61)"), "{stdout}");
+
+    let input = serde_json::to_string(&without(initial(true), 0)).unwrap();
+    let output = run(&["validate", "-0", "-p", "60", "-r", "3", "-o", "json"], &input);
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Partition(0) is missing"), "{stdout}");
+    assert!(!stdout.contains("Partition(60)"), "{stdout}");
+
+    let input = serde_json::to_string(&initial(false)).unwrap();
+    let output = run(&["validate", "-p", "60", "-r", "3"], &input);
+    assert!(output.status.success());
+}
+
+#[test]
+fn no_replicas() {
+    for (input, partitions, message) in [
+        ("{}", "2", "Partition(1) is missing"),
+        (r#"{"1": []}"#, "1", "Partition(1) replicas on 0 nodes"),
+    ] {
+        let output = run(&["validate", "-p", partitions, "-r", "1"], input);
+        assert!(!output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains(message), "{stdout}");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Invalid assignment"), "{stderr}");
+    }
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use assignment::{
+    Assignment, GreedyStrategy, Node, OptimalStrategy, RendezvousStrategy, Strategy, Topology,
+};
+use common::run;
+
+/// The nodes of zones of `sizes` nodes each, named by their zone and index in the zone.
+fn zones(sizes: &[usize]) -> (Vec<Node>, Topology) {
+    let mut all = vec![];
+    let mut topology = Topology::new();
+    for (z, &size) in sizes.iter().enumerate() {
+        for i in 0..size {
+            let n = Node::from(format!("z{z}n{i}"));
+            topology.set_zone(n.clone(), format!("z{z}"));
+            all.push(n);
+        }
+    }
+    (all, topology)
+}
+
+fn assert_valid(assignment: &Assignment, replication_factor: usize, topology: &Topology) {
+    let report = assignment.report(1, assignment.len(), replication_factor, topology, false);
+    assert!(!report.has_errors(), "{:?}\n{assignment:?}", report.violations());
+}
+
+#[test]
+fn uneven_zones_are_valid() {
+    // As many zones as replicas, so every zone takes one replica of each partition, and the nodes
+    // are added to and removed from the largest zone
+    let strategies: [&dyn Strategy; 3] = [&GreedyStrategy, &OptimalStrategy, &RendezvousStrategy];
+    for sizes in [&[2, 2, 3][..], &[1, 2, 4], &[1, 1, 3], &[2, 2, 5], &[1, 3, 3, 3]] {
+        let (all, topology) = zones(sizes);
+        let (adds, removes) = (&all[all.len() - 1..], &all[all.len() - 2..all.len() - 1]);
+        let replication_factor = sizes.len();
+        for strategy in strategies {
+            for partitions in [7, 12, 30, 61] {
+                let assignment = strategy
+                    .init(&all[..all.len() - 1], partitions, replication_factor, false, &topology)
+                    .unwrap();
+                assert_valid(&assignment, replication_factor, &topology);
+
+                let (added, _moves) = strategy.add(assignment, adds, &topology).unwrap();
+                assert_valid(&added, replication_factor, &topology);
+
+                let (removed, _moves) =
+                    strategy.remove(added, removes, replication_factor, &topology).unwrap();
+                assert_valid(&removed, replication_factor, &topology);
+            }
+        }
+    }
+}
+
+#[test]
+fn validate_uneven_zones() {
+    let topology = r#"{"a": {"zone": "z1"}, "b": {"zone": "z1"}, "g": {"zone": "z1"},
+        "c": {"zone": "z2"}, "d": {"zone": "z2"}, "e": {"zone": "z3"}, "f": {"zone": "z3"}}"#;
+    let path = format!("{}/uneven_zones.json", env!("CARGO_TARGET_TMPDIR"));
+    std::fs::write(&path, topology).unwrap();
+
+    let validate = |input: &[u8]| {
+        let input = String::from_utf8_lossy(input);
+        let output = run(&["validate", "-p", "30", "-r", "3", "-t", &path], &input);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{stdout}");
+    };
+
+    let args = ["-n", "a,b,c,d,e,f,g", "-p", "30", "-r", "3", "-t", &path, "-o", "json"];
+    let output = run(&[&["init"], &args[..]].concat(), "");
+    assert!(output.status.success());
+    validate(&output.stdout);
+
+    let input = String::from_utf8_lossy(&output.stdout).into_owned();
+    let output = run(&["add", "-n", "h", "-t", &path, "-o", "json"], &input);
+    assert!(output.status.success());
+    validate(&output.stdout);
+
+    let input = String::from_utf8_lossy(&output.stdout).into_owned();
+    let output = run(&["remove", "-n", "g,h", "-r", "3", "-t", &path, "-o", "json"], &input);
+    assert!(output.status.success());
+    validate(&output.stdout);
+}